@@ -0,0 +1,366 @@
+use crate::count::Counts;
+use crate::coord::Coord;
+use crate::tour::Tour;
+use crate::Board;
+use std::sync::mpsc::Sender;
+
+/// Largest board, holes included, the bitboard engine handles.
+pub const MAX_SQUARES: usize = 1024;
+
+/// A set of squares, one bit per square in `Shape::index_of` order.
+pub trait Bits: Copy + Send {
+    fn empty() -> Self;
+    fn with(self, i: usize) -> Self;
+    fn without(self, i: usize) -> Self;
+    fn has(&self, i: usize) -> bool;
+    fn and(self, other: Self) -> Self;
+    fn count(&self) -> u32;
+}
+
+impl Bits for u64 {
+    fn empty() -> Self {
+        0
+    }
+
+    fn with(self, i: usize) -> Self {
+        self | 1 << i
+    }
+
+    fn without(self, i: usize) -> Self {
+        self & !(1 << i)
+    }
+
+    fn has(&self, i: usize) -> bool {
+        self & 1 << i != 0
+    }
+
+    fn and(self, other: Self) -> Self {
+        self & other
+    }
+
+    fn count(&self) -> u32 {
+        self.count_ones()
+    }
+}
+
+/// Boards with more than 64 squares use several words.
+impl<const N: usize> Bits for [u64; N] {
+    fn empty() -> Self {
+        [0; N]
+    }
+
+    fn with(mut self, i: usize) -> Self {
+        self[i / 64] = self[i / 64].with(i % 64);
+        self
+    }
+
+    fn without(mut self, i: usize) -> Self {
+        self[i / 64] = self[i / 64].without(i % 64);
+        self
+    }
+
+    fn has(&self, i: usize) -> bool {
+        self[i / 64].has(i % 64)
+    }
+
+    fn and(mut self, other: Self) -> Self {
+        self.iter_mut().zip(other.iter()).for_each(|(a, b)| *a &= b);
+        self
+    }
+
+    fn count(&self) -> u32 {
+        self.iter().map(|w| w.count_ones()).sum()
+    }
+}
+
+/// The move orders the bitboard engine can reproduce exactly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BitOrder {
+    Warnsdorff,
+    Lexicographic,
+}
+
+/// The same search as `Board`, but with the visited squares kept as a bit
+/// set and the moves from every square worked out up front, so onward
+/// degrees are a popcount.
+struct BitBoard<B: Bits> {
+    /// Squares a move away from each square and the move getting there,
+    /// in the piece's move order.
+    targets: Vec<Vec<(usize, Coord)>>,
+    /// `targets` as sets.
+    attacks: Vec<B>,
+    /// Squares the end square can be reached from.
+    end_preds: B,
+    start: Coord,
+    start_idx: usize,
+    end: Option<usize>,
+    square_count: usize,
+    order: BitOrder,
+    free: B,
+    moves: Vec<Coord>,
+}
+
+impl<B: Bits> BitBoard<B> {
+    /// Picks up `board`'s rules and the moves it has already made.
+    fn new(board: &Board) -> BitBoard<B> {
+        let shape = &board.shape;
+        let mut targets = vec![Vec::new(); shape.len()];
+        let mut attacks = vec![B::empty(); shape.len()];
+        for from in shape.squares() {
+            let i = shape.index_of(from);
+            for &m in &board.moves {
+                if let Some(to) = board.step(from, m) {
+                    let t = shape.index_of(to);
+                    // On small wrapped boards two moves can land on the same square.
+                    if !attacks[i].has(t) {
+                        attacks[i] = attacks[i].with(t);
+                        targets[i].push((t, m));
+                    }
+                }
+            }
+        }
+        let mut end_preds = B::empty();
+        if let Some(end) = board.end {
+            for &m in &board.moves {
+                match board.step(end, m) {
+                    Some(c) if c != end && board.move_between(c, end).is_some() => {
+                        end_preds = end_preds.with(shape.index_of(c));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let free = shape
+            .squares()
+            .filter(|&c| board.can_move(c))
+            .fold(B::empty(), |acc, c| acc.with(shape.index_of(c)));
+        BitBoard {
+            targets,
+            attacks,
+            end_preds,
+            start: board.start,
+            start_idx: shape.index_of(board.start),
+            end: board.end.map(|e| shape.index_of(e)),
+            square_count: board.square_count(),
+            order: board.order.bit_order().expect("Order not supported by the bitboard engine"),
+            free,
+            moves: board.moves_made.clone(),
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.moves.len() + 1 == self.square_count
+    }
+
+    fn is_closed(&self, current: usize) -> bool {
+        self.attacks[current].has(self.start_idx)
+    }
+
+    fn tour(&self, current: usize) -> Tour {
+        Tour {
+            start: self.start,
+            moves: self.moves.clone(),
+            closed: self.is_closed(current),
+            restarts: 0,
+        }
+    }
+
+    /// The squares `Board::available_moves` would offer from `from`.
+    fn options(&self, from: usize) -> B {
+        let ret = self.attacks[from].and(self.free);
+        let end = match self.end {
+            Some(end) if !self.is_complete() => end,
+            _ => return ret,
+        };
+        // The end square is saved for the very last move, and has to stay
+        // reachable until then.
+        if self.moves.len() + 2 == self.square_count {
+            if ret.has(end) {
+                B::empty().with(end)
+            } else {
+                B::empty()
+            }
+        } else if self.end_preds.and(self.free).count() == 0 {
+            B::empty()
+        } else {
+            ret.without(end)
+        }
+    }
+
+    fn make_move(&mut self, to: usize, m: Coord) {
+        self.free = self.free.without(to);
+        self.moves.push(m);
+    }
+
+    fn rollback(&mut self, to: usize) {
+        self.free = self.free.with(to);
+        self.moves.pop();
+    }
+
+    fn search<F: FnMut(&Self, usize)>(&mut self, from: usize, on_complete: &mut F) {
+        if self.is_complete() {
+            on_complete(self, from);
+            return;
+        }
+        let options = self.options(from);
+        let mut candidates: Vec<(usize, Coord)> = self.targets[from]
+            .iter()
+            .copied()
+            .filter(|&(t, _)| options.has(t))
+            .collect();
+        if self.order == BitOrder::Warnsdorff {
+            // Fewest onward moves first; a stable sort keeps ties in move
+            // order, just like picking the first minimum each time.
+            let degrees: Vec<u32> = candidates
+                .iter()
+                .map(|&(t, m)| {
+                    self.make_move(t, m);
+                    let ret = self.options(t).count();
+                    self.rollback(t);
+                    ret
+                })
+                .collect();
+            let mut order: Vec<usize> = (0..candidates.len()).collect();
+            order.sort_by_key(|&i| degrees[i]);
+            candidates = order.into_iter().map(|i| candidates[i]).collect();
+        }
+        for (t, m) in candidates {
+            self.make_move(t, m);
+            self.search(t, on_complete);
+            self.rollback(t);
+        }
+    }
+}
+
+fn current_idx(board: &Board) -> usize {
+    board.shape.index_of(board.current)
+}
+
+fn do_loop_with<B: Bits>(board: &Board, sender: &Sender<Tour>) -> usize {
+    let mut found = 0;
+    let report = board.report;
+    BitBoard::<B>::new(board).search(current_idx(board), &mut |b, current| {
+        if report.wants(b.is_closed(current)) {
+            sender.send(b.tour(current)).unwrap();
+            found += 1;
+        }
+    });
+    found
+}
+
+fn count_with<B: Bits>(board: &Board) -> Counts {
+    let mut ret = Counts::default();
+    BitBoard::<B>::new(board).search(current_idx(board), &mut |b, current| {
+        if b.is_closed(current) {
+            ret.closed += 1;
+        } else {
+            ret.open += 1;
+        }
+    });
+    ret
+}
+
+/// Calls `$f` with the narrowest bit set that fits the board.
+macro_rules! with_bits {
+    ($board:expr, $f:ident ( $($arg:expr),* )) => {
+        match $board.shape.len().div_ceil(64) {
+            0..=1 => $f::<u64>($($arg),*),
+            2 => $f::<[u64; 2]>($($arg),*),
+            3..=4 => $f::<[u64; 4]>($($arg),*),
+            5..=8 => $f::<[u64; 8]>($($arg),*),
+            _ => $f::<[u64; 16]>($($arg),*),
+        }
+    };
+}
+
+/// `Board::do_loop` on the bitboard engine.
+pub fn do_loop(board: &Board, sender: &Sender<Tour>) -> usize {
+    with_bits!(board, do_loop_with(board, sender))
+}
+
+/// `count::count` on the bitboard engine.
+pub fn count(board: &Board) -> Counts {
+    with_bits!(board, count_with(board))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::Budget;
+    use crate::order::Lexicographic;
+    use crate::topology::Torus;
+    use crate::tour::Report;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+
+    /// Checks both engines send the same tours in the same order and
+    /// count the same totals.
+    fn engines_agree(board: Board) {
+        let mut expected = Vec::new();
+        board.clone().search(|b| {
+            if b.report.wants(b.is_closed_tour()) {
+                expected.push(b.tour());
+            }
+        });
+        let bits = board.clone().with_bitboard().unwrap();
+        let (tx, rx) = mpsc::channel();
+        assert_eq!(do_loop(&bits, &tx), expected.len());
+        drop(tx);
+        let found: Vec<Tour> = rx.iter().collect();
+        assert!(!found.is_empty());
+        for (a, b) in found.iter().zip(&expected) {
+            assert_eq!((a.start, &a.moves, a.closed), (b.start, &b.moves, b.closed));
+        }
+
+        let (slow, fast) = (crate::count::count(&board), crate::count::count(&bits));
+        assert_eq!((slow.closed, slow.open), (fast.closed, fast.open));
+    }
+
+    #[test]
+    fn plane_board() {
+        // Every tour of a 5x5 board from four squares in.
+        let prefix = [
+            Coord::xy(0, 0),
+            Coord::xy(1, 2),
+            Coord::xy(2, 0),
+            Coord::xy(4, 1),
+        ];
+        let board = Board::new(&[5, 5]).with_report(Report::All);
+        engines_agree(board.clone().with_prefix(&prefix).unwrap());
+        engines_agree(
+            board
+                .with_order(Arc::new(Lexicographic))
+                .with_prefix(&prefix)
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn wrapped_board() {
+        let board = Board::new(&[4, 3])
+            .with_topology(Arc::new(Torus))
+            .with_report(Report::All);
+        engines_agree(board.with_order(Arc::new(Lexicographic)));
+    }
+
+    #[test]
+    fn board_wider_than_one_word() {
+        // A prefix leaves few enough squares of a 9x9 board to walk them all.
+        let budget = Budget {
+            nodes: Some(1000),
+            ..Budget::default()
+        };
+        let mut first = None;
+        let mut board = Board::new(&[9, 9]).with_budget(budget);
+        board.search(|b| {
+            first.get_or_insert_with(|| b.positions(&b.tour()));
+        });
+        let prefix = &first.expect("No 9x9 tour within the budget")[..64];
+        engines_agree(
+            Board::new(&[9, 9])
+                .with_report(Report::All)
+                .with_prefix(prefix)
+                .unwrap(),
+        );
+    }
+}
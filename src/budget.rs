@@ -0,0 +1,110 @@
+use crate::coord::Coord;
+use crate::count::Counts;
+use crate::Board;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Limits on how much searching to do. Limits left unset never stop it.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Budget {
+    pub time: Option<Duration>,
+    /// Moves the search makes, counting every one it later takes back.
+    pub nodes: Option<u64>,
+    pub rollbacks: Option<u64>,
+}
+
+impl Budget {
+    pub fn is_limited(&self) -> bool {
+        self.time.is_some() || self.nodes.is_some() || self.rollbacks.is_some()
+    }
+}
+
+/// Why a search stopped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stopped {
+    /// Every branch was walked.
+    Exhausted,
+    Time,
+    Nodes,
+    Rollbacks,
+}
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Stopped::Exhausted => "every branch was searched",
+            Stopped::Time => "the time ran out",
+            Stopped::Nodes => "the node limit was reached",
+            Stopped::Rollbacks => "the rollback limit was reached",
+        })
+    }
+}
+
+/// How far a search has got.
+#[derive(Debug, Default, Clone)]
+pub struct Progress {
+    pub started: Option<Instant>,
+    pub nodes: u64,
+    pub rollbacks: u64,
+    /// The most moves made at once so far.
+    pub best: Vec<Coord>,
+    /// Every complete tour found.
+    pub counts: Counts,
+    /// Counts from start squares already searched in full, when counting
+    /// from each in turn.
+    pub finished: Vec<(Coord, Counts)>,
+}
+
+impl Progress {
+    pub fn elapsed(&self) -> Duration {
+        self.started.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+
+    /// The limit in `budget` that has been reached, if any.
+    pub fn over(&self, budget: &Budget) -> Option<Stopped> {
+        if budget.nodes.is_some_and(|n| self.nodes >= n) {
+            return Some(Stopped::Nodes);
+        }
+        if budget.rollbacks.is_some_and(|n| self.rollbacks >= n) {
+            return Some(Stopped::Rollbacks);
+        }
+        // Reading the clock costs more than a move, so only look now and then.
+        let due = (self.nodes + self.rollbacks).is_multiple_of(1024);
+        if due && budget.time.is_some_and(|t| self.elapsed() >= t) {
+            return Some(Stopped::Time);
+        }
+        None
+    }
+
+    /// Remembers `moves` if they're the furthest the search has got.
+    pub fn record(&mut self, moves: &[Coord]) {
+        if moves.len() > self.best.len() {
+            self.best = moves.to_vec();
+        }
+    }
+}
+
+/// Prints why the search on `board` stopped, how far it got and the longest
+/// path it found.
+pub fn print_report(board: &Board, found: usize, stopped: Stopped) {
+    let progress = &board.progress;
+    println!(
+        "Stopped because {} after {:.1}s, {} moves and {} rollbacks, with {} tours found",
+        stopped,
+        progress.elapsed().as_secs_f64(),
+        progress.nodes,
+        progress.rollbacks,
+        found
+    );
+    let squares: Vec<String> = board
+        .positions(&board.best_tour())
+        .into_iter()
+        .map(|c| board.show(c))
+        .collect();
+    println!(
+        "Longest path covers {} of {} squares: {}",
+        squares.len(),
+        board.square_count(),
+        squares.join(" ")
+    );
+}
@@ -0,0 +1,233 @@
+use crate::budget::Progress;
+use crate::coord::Coord;
+use crate::count::Counts;
+use crate::rng::Rng;
+use crate::Board;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Where and how often a running search saves itself.
+#[derive(Debug, Clone)]
+pub struct Saver {
+    pub path: String,
+    pub every: Duration,
+    pub last: Instant,
+    /// Also save straight after every tour sent on, so a search resumed
+    /// after being killed never sends one twice. Counting sends nothing,
+    /// so it leaves this off.
+    pub after_tours: bool,
+}
+
+/// Set when a checkpoint has been asked for, by SIGUSR1 or the S key.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the running search to save a checkpoint as soon as it can.
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// True, once, after a checkpoint has been asked for.
+pub fn take_request() -> bool {
+    REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+extern "C" fn on_signal(_: libc::c_int) {
+    request();
+}
+
+/// Makes SIGUSR1 ask for a checkpoint, e.g. `kill -USR1 <pid>`.
+pub fn listen_for_signal() {
+    // Storing to an atomic is all the handler does, which is safe in a
+    // signal handler.
+    unsafe {
+        libc::signal(
+            libc::SIGUSR1,
+            on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+/// Everything needed to carry on a search exactly where it left off,
+/// saved as JSON.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    // The problem being searched, which has to match on resuming.
+    dims: Vec<i16>,
+    holes: Vec<Coord>,
+    topology: String,
+    moves: Vec<Coord>,
+    end: Option<Coord>,
+    report: String,
+    order: String,
+    magic: String,
+    symmetry: String,
+
+    // Where the search had got to.
+    start: Coord,
+    floor: usize,
+    moves_made: Vec<Coord>,
+    moves_to_make: Vec<Vec<Coord>>,
+    rng: Rng,
+    rollbacks: usize,
+    restarts: usize,
+    nodes: u64,
+    rollbacks_total: u64,
+    best: Vec<Coord>,
+    counts: Counts,
+    finished: Vec<(Coord, Counts)>,
+}
+
+impl Checkpoint {
+    fn of(board: &Board) -> Checkpoint {
+        let shape = &board.shape;
+        Checkpoint {
+            dims: shape.dims.clone(),
+            holes: (0..shape.len())
+                .map(|i| shape.coord_of(i))
+                .filter(|&c| !shape.is_open(c))
+                .collect(),
+            topology: format!("{:?}", board.topology),
+            moves: board.moves.clone(),
+            end: board.end,
+            report: format!("{:?}", board.report),
+            order: format!("{:?}", board.order),
+            magic: format!("{:?}", board.magic),
+            symmetry: format!("{:?}", board.symmetry),
+            start: board.start,
+            floor: board.floor,
+            moves_made: board.moves_made.clone(),
+            moves_to_make: board.moves_to_make.clone(),
+            rng: board.rng.clone(),
+            rollbacks: board.rollbacks,
+            restarts: board.restarts,
+            nodes: board.progress.nodes,
+            rollbacks_total: board.progress.rollbacks,
+            best: board.progress.best.clone(),
+            counts: board.progress.counts,
+            finished: board.progress.finished.clone(),
+        }
+    }
+}
+
+/// Writes `board`'s search state to `path`. It goes to a temporary file
+/// first, so being killed part way through leaves the last checkpoint
+/// as it was.
+pub fn save(board: &Board, path: &str) -> Result<(), String> {
+    let json = serde_json::to_string(&Checkpoint::of(board)).map_err(|e| e.to_string())?;
+    let temp = format!("{}.tmp", path);
+    std::fs::write(&temp, json)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn load(path: &str) -> Result<Checkpoint, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Puts `board` back in the state saved in `cp`, after checking that
+/// the checkpoint is for the same problem.
+pub fn restore(board: Board, cp: Checkpoint) -> Result<Board, String> {
+    let saved = Checkpoint::of(&board);
+    let differences = [
+        ("board size", cp.dims != saved.dims),
+        ("shape", cp.holes != saved.holes),
+        ("topology", cp.topology != saved.topology),
+        ("piece", cp.moves != saved.moves),
+        ("end square", cp.end != saved.end),
+        ("tours reported", cp.report != saved.report),
+        ("move order", cp.order != saved.order),
+        ("magic sums", cp.magic != saved.magic),
+        ("symmetries", cp.symmetry != saved.symmetry),
+    ];
+    let differ: Vec<&str> = differences.iter().filter(|d| d.1).map(|d| d.0).collect();
+    if !differ.is_empty() {
+        return Err(format!(
+            "The checkpoint is for a different search: the {} differ",
+            differ.join(", ")
+        ));
+    }
+    let mut ret = board.with_start(cp.start)?;
+    for &m in &cp.moves_made {
+        if !ret.available_moves().contains(&m) {
+            return Err("The checkpoint's moves aren't a legal path".to_string());
+        }
+        ret.make_move(m);
+    }
+    // One list per move past the prefix and one for the square it's on,
+    // fewer once the search has backed out of some.
+    if cp.floor > cp.moves_made.len() || cp.moves_to_make.len() > cp.moves_made.len() - cp.floor + 1 {
+        return Err("The checkpoint's moves don't match its moves still to try".to_string());
+    }
+    ret.floor = cp.floor;
+    ret.moves_to_make = cp.moves_to_make;
+    ret.rng = cp.rng;
+    ret.rollbacks = cp.rollbacks;
+    ret.restarts = cp.restarts;
+    ret.progress = Progress {
+        started: None,
+        nodes: cp.nodes,
+        rollbacks: cp.rollbacks_total,
+        best: cp.best,
+        counts: cp.counts,
+        finished: cp.finished,
+    };
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::Budget;
+    use crate::tour::{Report, Tour};
+
+    fn tours_of(board: &mut Board, found: &mut Vec<Tour>) {
+        board.search(|b| {
+            if b.report.wants(b.is_closed_tour()) {
+                found.push(b.tour());
+            }
+        });
+    }
+
+    #[test]
+    fn resumed_search_skips_and_repeats_nothing() {
+        // Every tour of a 5x5 board from four squares in.
+        let prefix = [
+            Coord::xy(0, 0),
+            Coord::xy(1, 2),
+            Coord::xy(2, 0),
+            Coord::xy(4, 1),
+        ];
+        let board = Board::new(&[5, 5])
+            .with_report(Report::All)
+            .with_prefix(&prefix)
+            .unwrap();
+        let mut expected = Vec::new();
+        tours_of(&mut board.clone(), &mut expected);
+
+        // Stop every 2000 nodes, save, and carry on from the saved copy.
+        let mut found = Vec::new();
+        let mut stopped = board.clone();
+        let mut limit = 0;
+        loop {
+            limit += 2000;
+            let budget = Budget {
+                nodes: Some(limit),
+                ..Budget::default()
+            };
+            let json = serde_json::to_string(&Checkpoint::of(&stopped)).unwrap();
+            let cp = serde_json::from_str(&json).unwrap();
+            stopped = restore(board.clone().with_budget(budget), cp).unwrap();
+            tours_of(&mut stopped, &mut found);
+            if stopped.progress.nodes < limit {
+                break;
+            }
+        }
+        assert!(limit > 2000 && !expected.is_empty());
+        assert_eq!(found.len(), expected.len());
+        for (a, b) in found.iter().zip(&expected) {
+            assert_eq!((a.start, &a.moves, a.closed), (b.start, &b.moves, b.closed));
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::coord::Coord;
+use crate::tour::Tour;
+use crate::Board;
+use std::collections::HashMap;
+
+/// Blocks no bigger than this each way get a tour found by search.
+const MAX_BLOCK: i16 = 12;
+
+/// Search effort spent on a block from one start square before trying
+/// the next.
+const BLOCK_NODES: usize = 200_000;
+
+const KNIGHT: [(i16, i16); 8] = [
+    (1, 2),
+    (1, -2),
+    (2, 1),
+    (2, -1),
+    (-1, 2),
+    (-1, -2),
+    (-2, 1),
+    (-2, -1),
+];
+
+/// Builds a closed knight's tour of a `width`x`height` board without
+/// backtracking over the whole board, in the spirit of Parberry's and
+/// Conrad et al.'s divide-and-conquer constructions.
+///
+/// The board is halved across its longer side until the blocks are at
+/// most `MAX_BLOCK` squares each way, each block gets a closed tour from a
+/// small search (only done once per block size), and neighbouring tours
+/// are joined across each seam by swapping one edge of each for two edges
+/// crossing the seam. Everything apart from the block searches is linear
+/// in the number of squares, so 1000x1000 boards take moments.
+///
+/// The result is replayed on a `Board` and checked with
+/// `Board::is_closed_tour` before being returned.
+pub fn closed_tour(width: i16, height: i16) -> Result<Tour, String> {
+    if width < 5 || height < 5 {
+        return Err("Construction needs both sides to be at least 5".to_string());
+    }
+    if width % 2 == 1 && height % 2 == 1 {
+        return Err(format!("A {}x{} board has no closed tour", width, height));
+    }
+    let mut cycles = Cycles {
+        height: height as usize,
+        links: vec![[u32::MAX; 2]; width as usize * height as usize],
+        blocks: HashMap::new(),
+    };
+    cycles.build(0, 0, width, height)?;
+
+    let mut board = Board::new(&[width, height]);
+    let path: Vec<Coord> = cycles.walk().into_iter().map(|(x, y)| Coord::xy(x, y)).collect();
+    board = board.with_prefix(&path)?;
+    if !(board.is_complete() && board.is_closed_tour()) {
+        return Err("Construction did not produce a closed tour".to_string());
+    }
+    Ok(board.tour())
+}
+
+fn is_knight_move(a: (i16, i16), b: (i16, i16)) -> bool {
+    let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+    (dx, dy) == (1, 2) || (dx, dy) == (2, 1)
+}
+
+/// Closed tours over parts of the board, each square linked to the two
+/// squares either side of it on its tour.
+struct Cycles {
+    height: usize,
+    links: Vec<[u32; 2]>,
+    /// Tours already found for each block size, as squares in order.
+    blocks: HashMap<(i16, i16), Vec<(i16, i16)>>,
+}
+
+impl Cycles {
+    fn index_of(&self, (x, y): (i16, i16)) -> usize {
+        x as usize * self.height + y as usize
+    }
+
+    fn coord_of(&self, idx: u32) -> (i16, i16) {
+        ((idx as usize / self.height) as i16, (idx as usize % self.height) as i16)
+    }
+
+    fn link(&mut self, a: (i16, i16), b: (i16, i16)) {
+        let (ia, ib) = (self.index_of(a), self.index_of(b));
+        let slot = |l: &mut [u32; 2]| if l[0] == u32::MAX { 0 } else { 1 };
+        let sa = slot(&mut self.links[ia]);
+        self.links[ia][sa] = ib as u32;
+        let sb = slot(&mut self.links[ib]);
+        self.links[ib][sb] = ia as u32;
+    }
+
+    /// Swaps `a`'s link to `old` for one to `new`.
+    fn relink(&mut self, a: (i16, i16), old: (i16, i16), new: (i16, i16)) {
+        let (ia, iold, inew) = (self.index_of(a), self.index_of(old), self.index_of(new));
+        let l = &mut self.links[ia];
+        let slot = if l[0] == iold as u32 { 0 } else { 1 };
+        l[slot] = inew as u32;
+    }
+
+    fn neighbours(&self, a: (i16, i16)) -> [(i16, i16); 2] {
+        let l = self.links[self.index_of(a)];
+        [self.coord_of(l[0]), self.coord_of(l[1])]
+    }
+
+    /// Covers the `w`x`h` block at (`x0`, `y0`) with one closed tour.
+    fn build(&mut self, x0: i16, y0: i16, w: i16, h: i16) -> Result<(), String> {
+        if w <= MAX_BLOCK && h <= MAX_BLOCK {
+            let tour = match self.blocks.get(&(w, h)) {
+                Some(tour) => tour.clone(),
+                None => {
+                    let tour = block_tour(w, h)
+                        .ok_or_else(|| format!("No closed tour found for a {}x{} block", w, h))?;
+                    self.blocks.insert((w, h), tour.clone());
+                    tour
+                }
+            };
+            for (i, &(x, y)) in tour.iter().enumerate() {
+                let (nx, ny) = tour[(i + 1) % tour.len()];
+                self.link((x0 + x, y0 + y), (x0 + nx, y0 + ny));
+            }
+            return Ok(());
+        }
+        // Both halves need an even number of squares to have closed tours,
+        // and at least 6 along the cut side.
+        let split = |long: i16, short: i16| if short % 2 == 1 { (long / 2) & !1 } else { long / 2 };
+        if w >= h {
+            let left = split(w, h);
+            self.build(x0, y0, left, h)?;
+            self.build(x0 + left, y0, w - left, h)?;
+            self.join((x0, y0, w, h), true, x0 + left)
+        } else {
+            let top = split(h, w);
+            self.build(x0, y0, w, top)?;
+            self.build(x0, y0 + top, w, h - top)?;
+            self.join((x0, y0, w, h), false, y0 + top)
+        }
+    }
+
+    /// Merges the two tours either side of the seam at `cut` (a column if
+    /// `vertical`, otherwise a row) inside `rect`. Looks along the seam for
+    /// an edge a-b on the near side and c-d on the far side where a-c and
+    /// b-d are knight moves, then swaps the first two for the last two.
+    fn join(&mut self, rect: (i16, i16, i16, i16), vertical: bool, cut: i16) -> Result<(), String> {
+        let (x0, y0, w, h) = rect;
+        let far_side = |c: (i16, i16)| {
+            c.0 < x0 + w
+                && c.1 < y0 + h
+                && if vertical {
+                    c.0 >= cut && c.1 >= y0
+                } else {
+                    c.1 >= cut && c.0 >= x0
+                }
+        };
+        let (along, start) = if vertical { (h, y0) } else { (w, x0) };
+        for i in start..start + along {
+            for depth in 1..=2 {
+                let a = if vertical { (cut - depth, i) } else { (i, cut - depth) };
+                for b in self.neighbours(a) {
+                    for (dx, dy) in KNIGHT {
+                        let c = (a.0 + dx, a.1 + dy);
+                        if !far_side(c) {
+                            continue;
+                        }
+                        if let Some(d) = self.neighbours(c).iter().copied().find(|&d| is_knight_move(b, d)) {
+                            self.relink(a, b, c);
+                            self.relink(b, a, d);
+                            self.relink(c, d, a);
+                            self.relink(d, c, b);
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+        Err(format!("Couldn't join the tours across the seam at {}", cut))
+    }
+
+    /// The squares in tour order, starting from the corner.
+    fn walk(&self) -> Vec<(i16, i16)> {
+        let mut ret = Vec::with_capacity(self.links.len());
+        let (mut prev, mut current) = (u32::MAX, 0u32);
+        loop {
+            ret.push(self.coord_of(current));
+            let l = self.links[current as usize];
+            let next = if l[0] != prev { l[0] } else { l[1] };
+            prev = current;
+            current = next;
+            if current == 0 || ret.len() > self.links.len() {
+                break;
+            }
+        }
+        ret
+    }
+}
+
+/// A closed tour of a small `w`x`h` block by Warnsdorff's rule with
+/// backtracking, giving up on a start square after `BLOCK_NODES` nodes.
+fn block_tour(w: i16, h: i16) -> Option<Vec<(i16, i16)>> {
+    let squares = (0..w).flat_map(|x| (0..h).map(move |y| (x, y)));
+    // Closed tours pass through every square, so any start will do; some
+    // just find one much faster than others.
+    for start in squares {
+        let mut visited = vec![vec![false; h as usize]; w as usize];
+        visited[start.0 as usize][start.1 as usize] = true;
+        let mut path = vec![start];
+        let mut nodes = BLOCK_NODES;
+        if block_search(w, h, &mut visited, &mut path, &mut nodes) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+fn block_search(
+    w: i16,
+    h: i16,
+    visited: &mut Vec<Vec<bool>>,
+    path: &mut Vec<(i16, i16)>,
+    nodes: &mut usize,
+) -> bool {
+    let current = *path.last().unwrap();
+    if path.len() == w as usize * h as usize {
+        return is_knight_move(current, path[0]);
+    }
+    if *nodes == 0 {
+        return false;
+    }
+    *nodes -= 1;
+    let free = |visited: &Vec<Vec<bool>>, (x, y): (i16, i16)| {
+        x >= 0 && x < w && y >= 0 && y < h && !visited[x as usize][y as usize]
+    };
+    let onward = |visited: &Vec<Vec<bool>>, c: (i16, i16)| {
+        KNIGHT
+            .iter()
+            .filter(|&&(dx, dy)| free(visited, (c.0 + dx, c.1 + dy)))
+            .count()
+    };
+    let mut candidates: Vec<(i16, i16)> = KNIGHT
+        .iter()
+        .map(|&(dx, dy)| (current.0 + dx, current.1 + dy))
+        .filter(|&c| free(visited, c))
+        .collect();
+    candidates.sort_by_key(|&c| onward(visited, c));
+    for c in candidates {
+        visited[c.0 as usize][c.1 as usize] = true;
+        path.push(c);
+        if block_search(w, h, visited, path, nodes) {
+            return true;
+        }
+        path.pop();
+        visited[c.0 as usize][c.1 as usize] = false;
+    }
+    false
+}
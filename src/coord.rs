@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use crate::topology::Topology;
+use std::ops::{Add, Index, IndexMut};
+
+/// Most axes a board can have.
+pub const MAX_DIMS: usize = 6;
+
+/// A square, or a move between squares, on a board with up to `MAX_DIMS`
+/// axes. Axes the board doesn't have are always zero.
+#[derive(Hash, Eq, PartialEq, Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Coord(pub [i16; MAX_DIMS]);
+
+impl Coord {
+    pub fn new(xs: &[i16]) -> Coord {
+        assert!(xs.len() <= MAX_DIMS, "At most {} dimensions", MAX_DIMS);
+        let mut ret = Coord::default();
+        ret.0[..xs.len()].copy_from_slice(xs);
+        ret
+    }
+
+    pub fn xy(x: i16, y: i16) -> Coord {
+        Coord::new(&[x, y])
+    }
+
+    pub fn x(&self) -> i16 {
+        self.0[0]
+    }
+
+    pub fn y(&self) -> i16 {
+        self.0[1]
+    }
+}
+
+impl Index<usize> for Coord {
+    type Output = i16;
+
+    fn index(&self, axis: usize) -> &i16 {
+        &self.0[axis]
+    }
+}
+
+impl IndexMut<usize> for Coord {
+    fn index_mut(&mut self, axis: usize) -> &mut i16 {
+        &mut self.0[axis]
+    }
+}
+
+impl Add<Coord> for Coord {
+    type Output = Coord;
+
+    fn add(mut self, rhs: Coord) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl Add<&Coord> for Coord {
+    type Output = Coord;
+
+    fn add(self, rhs: &Coord) -> Self::Output {
+        self + *rhs
+    }
+}
+
+impl std::ops::SubAssign for Coord {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0.iter_mut().zip(rhs.0.iter()).for_each(|(a, b)| *a -= b)
+    }
+}
+
+impl std::ops::AddAssign for Coord {
+    fn add_assign(&mut self, rhs: Coord) {
+        self.0.iter_mut().zip(rhs.0.iter()).for_each(|(a, b)| *a += b)
+    }
+}
+
+/// Every knight move on a board with `dims` axes: ±1 along one axis and ±2
+/// along another. On a 2D board this is the usual eight moves, in the order
+/// the search has always tried them.
+pub fn knight_moves(dims: usize) -> Vec<Coord> {
+    leaper_moves(1, 2, dims).unwrap()
+}
+
+/// Every move of an (a,b)-leaper on a board with `dims` axes: ±a along one
+/// axis and ±b along another, e.g. (1,3) for the camel. Fails if `a` or `b`
+/// can't be negated.
+pub fn leaper_moves(a: i16, b: i16, dims: usize) -> Result<Vec<Coord>, String> {
+    assert!((2..=MAX_DIMS).contains(&dims), "Boards need 2 to {} dimensions", MAX_DIMS);
+    if a == i16::MIN || b == i16::MIN {
+        return Err(format!("Leaper {},{} is too long for any board", a, b));
+    }
+    let mut leap: Vec<i16> = [a.abs(), b.abs()].iter().copied().filter(|&x| x != 0).collect();
+    leap.sort_unstable();
+    let steps = [a, b, -a, -b, 0];
+    let mut ret: Vec<Coord> = Vec::new();
+    let mut digits = vec![0usize; dims];
+    loop {
+        let v: Vec<i16> = digits.iter().map(|&d| steps[d]).collect();
+        let mut used: Vec<i16> = v.iter().map(|x| x.abs()).filter(|&x| x != 0).collect();
+        used.sort_unstable();
+        let c = Coord::new(&v);
+        // With a == b or a == 0 the same vector turns up more than once.
+        if used == leap && !ret.contains(&c) {
+            ret.push(c);
+        }
+        // Count through every vector, last axis fastest.
+        match digits.iter().rposition(|&d| d + 1 < steps.len()) {
+            Some(axis) => {
+                digits[axis] += 1;
+                digits[axis + 1..].iter_mut().for_each(|d| *d = 0);
+            }
+            None => break,
+        }
+    }
+    Ok(ret)
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Rejects move sets that can never produce a tour on a board of size
+/// `dims` glued together by `topology`.
+pub fn check_moves(moves: &[Coord], dims: &[i16], topology: &dyn Topology) -> Result<(), String> {
+    if moves.is_empty() {
+        return Err("The piece has no moves".to_string());
+    }
+    if let Some(m) = moves.iter().find(|m| m.0[dims.len()..].iter().any(|&x| x != 0)) {
+        return Err(format!(
+            "Move {:?} uses more than the board's {} dimensions",
+            &m.0[..],
+            dims.len()
+        ));
+    }
+    // Adding a longer move to a square could overflow.
+    let largest = dims.iter().map(|&d| i32::from(d)).max().unwrap_or(0);
+    let longest = |limit: i32| {
+        moves
+            .iter()
+            .find(move |m| m.0.iter().any(|&x| i32::from(x).abs() > limit))
+    };
+    if let Some(m) = longest(i32::from(i16::MAX) - largest) {
+        return Err(format!(
+            "Move {:?} is too long for a board this size",
+            &m.0[..dims.len()]
+        ));
+    }
+    if moves.iter().any(|m| *m == Coord::default()) {
+        return Err("A move has to go somewhere".to_string());
+    }
+    // Wrapping round an odd side changes colours and strides, and lets
+    // long moves land.
+    if !topology.is_plane() {
+        return Ok(());
+    }
+    if let Some(m) = longest(largest) {
+        return Err(format!(
+            "Move {:?} is longer than the board's largest side of {}",
+            &m.0[..dims.len()],
+            largest
+        ));
+    }
+    // Each move changes the colour of the square iff its coordinates have
+    // an odd sum, so with none of those half the board is out of reach.
+    if moves
+        .iter()
+        .all(|m| m.0.iter().map(|&x| i32::from(x)).sum::<i32>() % 2 == 0)
+    {
+        return Err(
+            "The piece is colour-bound (every move has an even coordinate sum, e.g. a+b even), \
+             so it can never visit every square"
+                .to_string(),
+        );
+    }
+    let g = moves
+        .iter()
+        .flat_map(|m| m.0.iter())
+        .fold(0, |g, &x| gcd(g, i32::from(x)));
+    if g > 1 {
+        return Err(format!(
+            "Every move is a multiple of {} (gcd(a,b) > 1), so the piece can never visit every square",
+            g
+        ));
+    }
+    Ok(())
+}
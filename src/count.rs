@@ -0,0 +1,117 @@
+use crate::bitboard;
+use crate::coord::Coord;
+use crate::order::Lexicographic;
+use crate::parallel;
+use crate::Board;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::sync::Arc;
+
+/// Directed tours found by an exhaustive search.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Counts {
+    pub closed: u64,
+    pub open: u64,
+}
+
+impl Counts {
+    /// Tallies the complete tour `board` has just made.
+    pub fn add(&mut self, board: &Board) {
+        let closed = board.is_closed_tour();
+        // Parts of symmetric tours that don't close up aren't tours at all.
+        if closed || board.symmetry.is_none() {
+            self.tally(closed);
+        }
+    }
+
+    pub fn tally(&mut self, closed: bool) {
+        if closed {
+            self.closed += 1;
+        } else {
+            self.open += 1;
+        }
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, rhs: Counts) {
+        self.closed += rhs.closed;
+        self.open += rhs.open;
+    }
+}
+
+/// Walks the whole search space from `board` as it stands.
+pub fn count(board: &Board) -> Counts {
+    // Every branch gets visited whatever the order, so don't pay for a
+    // heuristic, never give up on a branch by starting again and only
+    // count what the search itself finds.
+    let mut board = board.clone().with_order(Arc::new(Lexicographic));
+    board.restart_after = None;
+    board.repair = false;
+    if board.bitboard {
+        return bitboard::count(&board);
+    }
+    let mut ret = Counts::default();
+    if board.threads > 1 {
+        parallel::search(&board, |acc: &mut Counts, b| acc.add(b))
+            .into_iter()
+            .for_each(|c| ret += c);
+    } else {
+        // The board keeps count itself, including before any checkpoint
+        // it was resumed from.
+        board.search(|_| {});
+        ret = board.progress.counts;
+    }
+    ret
+}
+
+/// Counts the tours from each start square in turn, or only from
+/// `board`'s start if `one_start` is set, e.g. for a start square the user
+/// chose or a prefix. A board resumed from a checkpoint carries on from
+/// the start square it had got to.
+pub fn by_start(board: &Board, one_start: bool) -> Vec<(Coord, Counts)> {
+    if one_start || board.floor > 0 {
+        return vec![(board.start, count(board))];
+    }
+    let mut ret = board.progress.finished.clone();
+    for s in board.shape.squares().filter(|&s| board.end != Some(s)) {
+        if ret.iter().any(|r| r.0 == s) {
+            continue;
+        }
+        let mut b = if s == board.start {
+            board.clone()
+        } else {
+            board.clone().with_start(s).expect("Start is on the board")
+        };
+        // Checkpoints of this start need the others done so far.
+        b.progress.finished = ret.clone();
+        ret.push((s, count(&b)));
+    }
+    ret
+}
+
+pub fn print_report(board: &Board, results: &[(Coord, Counts)]) {
+    let mut total = Counts::default();
+    for &(start, counts) in results {
+        println!(
+            "{}: {} closed, {} open",
+            board.show(start),
+            counts.closed,
+            counts.open
+        );
+        total += counts;
+    }
+    // A closed tour passes through every square, so searching from each
+    // finds it once from each. Any one start has them all.
+    let every_start = results.len() == board.square_count();
+    let closed = if every_start {
+        results.first().map_or(0, |r| r.1.closed)
+    } else {
+        total.closed
+    };
+    println!("Directed tours: {} closed, {} open", closed, total.open);
+    // Each undirected tour is found once in each direction.
+    if board.is_reversible() && every_start && board.end.is_none() {
+        println!("Undirected tours: {} closed, {} open", closed / 2, total.open / 2);
+    }
+}
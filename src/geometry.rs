@@ -0,0 +1,55 @@
+use crate::coord::Coord;
+
+/// Twice the signed area of the triangle `a`, `b`, `c`: positive if the
+/// path a→b→c turns left, negative if it turns right and 0 if straight.
+fn turn(a: Coord, b: Coord, c: Coord) -> i64 {
+    let (abx, aby) = ((b.x() - a.x()) as i64, (b.y() - a.y()) as i64);
+    let (acx, acy) = ((c.x() - a.x()) as i64, (c.y() - a.y()) as i64);
+    abx * acy - aby * acx
+}
+
+/// True if `c`, which is in line with `a` and `b`, lies between them.
+fn between(a: Coord, b: Coord, c: Coord) -> bool {
+    a.x().min(b.x()) <= c.x()
+        && c.x() <= a.x().max(b.x())
+        && a.y().min(b.y()) <= c.y()
+        && c.y() <= a.y().max(b.y())
+}
+
+/// True if the segments joining the centres of `a` to `b` and `c` to `d`
+/// have any point in common, touching included.
+pub fn crosses(a: Coord, b: Coord, c: Coord, d: Coord) -> bool {
+    let (d1, d2) = (turn(c, d, a), turn(c, d, b));
+    let (d3, d4) = (turn(a, b, c), turn(a, b, d));
+    if d1.signum() * d2.signum() < 0 && d3.signum() * d4.signum() < 0 {
+        return true;
+    }
+    (d1 == 0 && between(c, d, a))
+        || (d2 == 0 && between(c, d, b))
+        || (d3 == 0 && between(a, b, c))
+        || (d4 == 0 && between(a, b, d))
+}
+
+/// The segments of `path` the segment from `a` to `b` meets, not counting
+/// those sharing one of its ends.
+fn met(path: &[Coord], a: Coord, b: Coord) -> impl Iterator<Item = (&Coord, &Coord)> {
+    path.iter()
+        .zip(path.iter().skip(1))
+        .filter(move |&(&c, &d)| c != a && c != b && d != a && d != b && crosses(a, b, c, d))
+}
+
+/// How many segments of `path` the segment from `a` to `b` meets.
+pub fn crossings(path: &[Coord], a: Coord, b: Coord) -> usize {
+    met(path, a, b).count()
+}
+
+/// True if the segment from `a` to `b` meets any segment of `path`.
+pub fn crosses_path(path: &[Coord], a: Coord, b: Coord) -> bool {
+    met(path, a, b).next().is_some()
+}
+
+/// Distance between the centres of `a` and `b`.
+pub fn length(a: Coord, b: Coord) -> f64 {
+    let (dx, dy) = ((b.x() - a.x()) as f64, (b.y() - a.y()) as f64);
+    dx.hypot(dy)
+}
@@ -0,0 +1,136 @@
+use crate::coord::Coord;
+use crate::tour::Tour;
+use crate::Board;
+
+/// How magic the grid of move numbers of a tour is, numbering the start 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Magic {
+    /// Every row adds up to the same number, and so does every column.
+    SemiMagic,
+    /// Semi-magic, and both long diagonals add up to the same as the rows.
+    Magic,
+}
+
+impl Magic {
+    pub fn by_name(name: &str) -> Option<Magic> {
+        match name {
+            "semi" => Some(Magic::SemiMagic),
+            "full" => Some(Magic::Magic),
+            _ => None,
+        }
+    }
+
+    /// Fails unless magic tours could exist on `board`'s shape.
+    pub fn check(self, board: &Board) -> Result<(), String> {
+        let shape = &board.shape;
+        if shape.dims.len() != 2 || board.square_count() != shape.len() {
+            return Err("Magic tours need a plain 2D rectangle".to_string());
+        }
+        let (w, h) = (shape.width() as u64, shape.height() as u64);
+        let total = (w * h) * (w * h + 1) / 2;
+        if total % w != 0 || total % h != 0 {
+            return Err(format!(
+                "The numbers 1 to {} can't be shared equally between {} rows and {} columns",
+                w * h,
+                h,
+                w
+            ));
+        }
+        if self == Magic::Magic && w != h {
+            return Err("Fully magic tours need a square board".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Magic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Magic::SemiMagic => "semi-magic",
+            Magic::Magic => "magic",
+        })
+    }
+}
+
+/// Every line that has to add up: the rows, then the columns, then the
+/// diagonals on square boards.
+fn lines(width: i16, height: i16) -> Vec<Vec<Coord>> {
+    let mut ret: Vec<Vec<Coord>> = Vec::new();
+    ret.extend((0..height).map(|y| (0..width).map(|x| Coord::xy(x, y)).collect()));
+    ret.extend((0..width).map(|x| (0..height).map(|y| Coord::xy(x, y)).collect()));
+    if width == height {
+        ret.push((0..width).map(|i| Coord::xy(i, i)).collect());
+        ret.push((0..width).map(|i| Coord::xy(i, width - 1 - i)).collect());
+    }
+    ret
+}
+
+/// Whether `board`'s next move can go to `to` and still leave every row
+/// and column (and the diagonals for `Magic`) able to reach its sum. The
+/// squares still empty in a line will get some of the numbers after this
+/// one, so each line needs its total to lie between what the smallest and
+/// the largest of those would add.
+pub fn allows(board: &Board, to: Coord, magic: Magic) -> bool {
+    let (w, h) = (board.shape.width() as usize, board.shape.height() as usize);
+    let n = (w * h) as u64;
+    let value = board.moves_made.len() as u64 + 2;
+    let total = n * (n + 1) / 2;
+    // Sums and empty squares of the rows, columns and two diagonals.
+    let mut sum = vec![0u64; w + h + 2];
+    let mut empty = vec![0u64; w + h + 2];
+    for y in 0..h {
+        for x in 0..w {
+            let c = Coord::xy(x as i16, y as i16);
+            let v = if c == to { value } else { board.value_at(c) as u64 };
+            let mut add = |line: usize| {
+                sum[line] += v;
+                empty[line] += (v == 0) as u64;
+            };
+            add(y);
+            add(h + x);
+            if w == h && x == y {
+                add(w + h);
+            }
+            if w == h && x + y == w - 1 {
+                add(w + h + 1);
+            }
+        }
+    }
+    let checked = if magic == Magic::Magic { w + h + 2 } else { w + h };
+    (0..checked).all(|line| {
+        let target = if line < h { total / h as u64 } else { total / w as u64 };
+        let k = empty[line];
+        // The smallest and largest numbers to come that could fill it.
+        let least = (value + 1..=value + k).sum::<u64>();
+        let most = (n + 1 - k..=n).sum::<u64>();
+        sum[line] + least <= target && target <= sum[line] + most
+    })
+}
+
+/// How magic the numbering of the complete `tour` is, if at all.
+pub fn kind(board: &Board, tour: &Tour) -> Option<Magic> {
+    let shape = &board.shape;
+    if shape.dims.len() != 2 || board.square_count() != shape.len() {
+        return None;
+    }
+    let (w, h) = (shape.width(), shape.height());
+    let mut numbers = vec![0u64; shape.len()];
+    for (i, c) in board.positions(tour).into_iter().enumerate() {
+        numbers[shape.index_of(c)] = i as u64 + 1;
+    }
+    let sums: Vec<u64> = lines(w, h)
+        .iter()
+        .map(|line| line.iter().map(|&c| numbers[shape.index_of(c)]).sum())
+        .collect();
+    let (rows, rest) = sums.split_at(h as usize);
+    let (columns, diagonals) = rest.split_at(w as usize);
+    let equal = |xs: &[u64]| xs.iter().all(|&x| x == xs[0]);
+    if !(equal(rows) && equal(columns)) {
+        return None;
+    }
+    if w == h && equal(diagonals) && diagonals[0] == rows[0] {
+        Some(Magic::Magic)
+    } else {
+        Some(Magic::SemiMagic)
+    }
+}
@@ -1,54 +1,89 @@
+mod bitboard;
+mod budget;
+mod checkpoint;
+mod construct;
+mod coord;
+mod count;
 mod experiment;
+mod geometry;
+mod magic;
 mod my_serde;
+mod neural;
+mod optimise;
+mod order;
+mod parallel;
+mod piece;
+mod repair;
+mod rng;
+mod sat;
+mod shape;
+mod symmetry;
+mod topology;
+mod tour;
+mod uncrossed;
 
+use budget::{Budget, Progress, Stopped};
+use checkpoint::{Checkpoint, Saver};
+use coord::Coord;
+use magic::Magic;
+use optimise::Goal;
+use order::MoveOrder;
+use rng::Rng;
 use sdl2::event::Event;
 use sdl2::gfx::primitives::DrawRenderer;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use std::ops::Add;
+use shape::Shape;
+use symmetry::Symmetry;
 use std::sync::mpsc;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use topology::Topology;
+use tour::{Report, Tour};
 
-//use std::sync::mpsc::Sender;
-#[derive(Hash, Eq, PartialEq, Debug, Copy, Clone)]
-struct Coord(i8, i8);
-
-impl Add<Coord> for Coord {
-    type Output = Coord;
-
-    fn add(self, rhs: Coord) -> Self::Output {
-        Coord(self.0 + rhs.0, self.1 + rhs.1)
-    }
-}
-
-impl Add<&Coord> for Coord {
-    type Output = Coord;
-
-    fn add(self, rhs: &Coord) -> Self::Output {
-        Coord(self.0 + rhs.0, self.1 + rhs.1)
-    }
-}
-
-impl std::ops::SubAssign for Coord {
-    fn sub_assign(&mut self, rhs: Self) {
-        *self = Self(self.0 - rhs.0, self.1 - rhs.1)
-    }
-}
-
-impl std::ops::AddAssign for Coord {
-    fn add_assign(&mut self, rhs: Coord) {
-        *self = Coord(self.0 + rhs.0, self.1 + rhs.1)
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Board {
+    shape: Shape,
+    topology: Arc<dyn Topology>,
+    start: Coord,
+    end: Option<Coord>,
     moves_made: Vec<Coord>,
+    /// Moves at the start of `moves_made` the search may not undo.
+    floor: usize,
+    visited: Vec<Coord>,
     current: Coord,
     moves_to_make: Vec<Vec<Coord>>,
-    board: [i8; 64],
-    moves: [Coord; 8],
+    board: Vec<usize>,
+    moves: Vec<Coord>,
+    report: Report,
+    order: Arc<dyn MoveOrder>,
+    rng: Rng,
+    threads: usize,
+    split_depth: usize,
+    bitboard: bool,
+    /// Rollbacks after which the search starts again from the prefix.
+    restart_after: Option<usize>,
+    rollbacks: usize,
+    /// Restarts since the last tour of the kind reported.
+    restarts: usize,
+    budget: Budget,
+    progress: Progress,
+    saver: Option<Saver>,
+    /// Try to close each complete open path with `repair::close`.
+    repair: bool,
+    /// Only make tours whose move numbers are this magic.
+    magic: Option<Magic>,
+    /// Only make closed tours with this symmetry, searching for the first
+    /// part of the tour and marking its images as it goes.
+    symmetry: Option<Symmetry>,
+    /// Look for the tour scoring best on this rather than every tour.
+    goal: Option<Goal>,
+    /// Look for the longest path that never crosses itself instead of tours.
+    uncrossed: bool,
+    /// Carrying on a search from a checkpoint.
+    resumed: bool,
 }
 
 #[derive(Debug)]
@@ -59,98 +94,534 @@ enum Mutation {
 }
 
 impl Board {
-    pub fn value_at(&self, coord: Coord) -> i8 {
-        self.board[Board::index_of(coord)]
+    pub fn value_at(&self, coord: Coord) -> usize {
+        self.board[self.index_of(coord)]
     }
 
-    fn index_of(coord: Coord) -> usize {
-        (coord.0 * 8 + coord.1) as usize
+    fn index_of(&self, coord: Coord) -> usize {
+        self.shape.index_of(coord)
     }
 
-    pub fn set_value_at(&mut self, coord: Coord, val: i8) {
-        self.board[Board::index_of(coord)] = val
+    pub fn set_value_at(&mut self, coord: Coord, val: usize) {
+        let idx = self.index_of(coord);
+        self.board[idx] = val
     }
 
-    pub fn new() -> Board {
+    /// A full board with one entry of `dims` per axis, e.g. `[8, 8]` or `[4, 4, 4]`.
+    pub fn new(dims: &[i16]) -> Board {
+        Board::with_shape(Shape::cuboid(dims))
+    }
+
+    pub fn with_shape(shape: Shape) -> Board {
+        let start = shape.first_open().expect("Shape has no available squares");
+        let squares = shape.len();
+        let moves = coord::knight_moves(shape.dims.len());
         let mut ret = Board {
+            shape,
+            topology: Arc::new(topology::Plane),
+            start,
+            end: None,
             moves_made: Vec::new(),
-            current: Coord(0, 0),
+            floor: 0,
+            visited: Vec::new(),
+            current: start,
             moves_to_make: Vec::new(),
-            board: [0; 64],
-            moves: {
-                let combs = [1i8, 2, -1, -2];
-                let mut ret = [Coord(0, 0); 8];
-                combs
-                    .iter()
-                    .flat_map(|i| combs.iter().map(move |j| Coord(*i, *j)))
-                    .filter(|c| c.0.abs() != c.1.abs())
-                    .enumerate()
-                    .for_each(|(i, x)| {
-                        ret[i] = x;
-                    });
-                ret
-            },
+            board: vec![0; squares],
+            moves,
+            report: Report::Closed,
+            order: Arc::new(order::Warnsdorff),
+            rng: Rng::new(0),
+            threads: 1,
+            split_depth: 0,
+            bitboard: false,
+            restart_after: None,
+            rollbacks: 0,
+            restarts: 0,
+            budget: Budget::default(),
+            progress: Progress::default(),
+            saver: None,
+            repair: false,
+            magic: None,
+            symmetry: None,
+            goal: None,
+            uncrossed: false,
+            resumed: false,
         };
-        ret.moves_to_make.push(ret.available_moves());
+        ret.reset();
         ret
     }
 
-    pub fn is_on_board(c: Coord) -> bool {
-        c.0 >= 0 && c.0 < 8 && c.1 >= 0 && c.1 < 8
+    /// Starts tours from `start` rather than the first available square.
+    pub fn with_start(mut self, start: Coord) -> Result<Board, String> {
+        if !self.is_on_board(start) {
+            return Err(format!("Start square {} is not on the board", self.show(start)));
+        }
+        if self.end == Some(start) {
+            return Err(format!("Tours can't start and end on {}", self.show(start)));
+        }
+        self.start = start;
+        self.reset();
+        Ok(self)
+    }
+
+    /// Only accepts tours that finish on `end`.
+    pub fn with_end(mut self, end: Coord) -> Result<Board, String> {
+        if !self.is_on_board(end) {
+            return Err(format!("End square {} is not on the board", self.show(end)));
+        }
+        if end == self.start {
+            return Err(format!("Tours can't start and end on {}", self.show(end)));
+        }
+        self.end = Some(end);
+        self.reset();
+        Ok(self)
+    }
+
+    /// Plays `path` as the opening of every tour, its first square being
+    /// the start. The search extends it but never backs out of it. Call
+    /// this after the other `with_` methods, which start the search afresh.
+    pub fn with_prefix(self, path: &[Coord]) -> Result<Board, String> {
+        let first = *path.first().ok_or("The prefix is empty")?;
+        let mut ret = self.with_start(first)?;
+        for &to in &path[1..] {
+            let m = ret
+                .available_moves()
+                .into_iter()
+                .find(|&m| ret.step(ret.current, m) == Some(to))
+                .ok_or_else(|| {
+                    format!(
+                        "Prefix can't go from {} to {}: not a legal move, already visited or saved for the end",
+                        ret.show(ret.current),
+                        ret.show(to)
+                    )
+                })?;
+            ret.make_move(m);
+        }
+        ret.pin_prefix();
+        Ok(ret)
+    }
+
+    /// Makes the moves played so far a prefix the search can't undo.
+    fn pin_prefix(&mut self) {
+        self.floor = self.moves_made.len();
+        self.moves_to_make = vec![self.available_moves()];
+    }
+
+    /// `c` as a list of coordinates, including any axes the board doesn't
+    /// have that `c` isn't zero along.
+    fn show(&self, c: Coord) -> String {
+        let used = c.0.iter().rposition(|&x| x != 0).map_or(0, |i| i + 1);
+        format!("{:?}", &c.0[..used.max(self.shape.dims.len())])
+    }
+
+    pub fn with_topology(mut self, topology: Arc<dyn Topology>) -> Board {
+        self.topology = topology;
+        self.reset();
+        self
+    }
+
+    /// Chooses whether open tours, closed tours or both are sent on.
+    pub fn with_report(mut self, report: Report) -> Board {
+        self.report = report;
+        self
+    }
+
+    /// Chooses the heuristic deciding which move to try next.
+    pub fn with_order(mut self, order: Arc<dyn MoveOrder>) -> Board {
+        self.order = order;
+        self
+    }
+
+    /// Seeds the random numbers used by randomised move orders.
+    pub fn with_seed(mut self, seed: u64) -> Board {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Searches on `threads` threads, splitting the tree into subtrees
+    /// `split_depth` moves down.
+    pub fn with_threads(mut self, threads: usize, split_depth: usize) -> Board {
+        self.threads = threads;
+        self.split_depth = split_depth;
+        self
+    }
+
+    /// Runs the search on the bitboard engine, which finds the same tours
+    /// in the same order, only faster. It only does the warnsdorff and
+    /// lexicographic orders, on a single thread, with none of the extras.
+    pub fn with_bitboard(mut self) -> Result<Board, String> {
+        if self.shape.len() > bitboard::MAX_SQUARES {
+            return Err(format!(
+                "The bitboard engine handles boards of up to {} squares",
+                bitboard::MAX_SQUARES
+            ));
+        }
+        self.bitboard = true;
+        Ok(self)
+    }
+
+    /// Starts the search again from the prefix after every `rollbacks`
+    /// rollbacks, rather than digging out of a bad opening. Only works
+    /// with a random move order, so each attempt goes a different way, and
+    /// on a single thread, so the seed decides every attempt.
+    pub fn with_restarts(mut self, rollbacks: usize) -> Result<Board, String> {
+        if rollbacks == 0 {
+            return Err("Restarts need at least one rollback between them".to_string());
+        }
+        self.restart_after = Some(rollbacks);
+        Ok(self)
+    }
+
+    /// Stops the search once any limit in `budget` is reached, however far
+    /// it has got. Only works on a single thread without the bitboard
+    /// engine.
+    pub fn with_budget(mut self, budget: Budget) -> Board {
+        self.budget = budget;
+        self
+    }
+
+    /// Saves the search state to `path` every so often, and when the search
+    /// stops, so it can be resumed with `with_resume`. Only works on a
+    /// single thread without the bitboard engine.
+    pub fn with_checkpoints(mut self, path: &str, every: Duration) -> Board {
+        self.saver = Some(Saver {
+            path: path.to_string(),
+            every,
+            last: Instant::now(),
+            after_tours: false,
+        });
+        self
+    }
+
+    /// Carries on the search saved in `cp`, which has to be for the same
+    /// board, piece and move order. Call this after the other `with_`
+    /// methods.
+    pub fn with_resume(mut self, cp: Checkpoint) -> Result<Board, String> {
+        self.resumed = true;
+        checkpoint::restore(self, cp)
+    }
+
+    /// Tries to turn every complete open path into a closed tour, which is
+    /// reported as well as the open one. Needs a piece that can go back
+    /// the way it came and no end square.
+    pub fn with_repair(mut self) -> Result<Board, String> {
+        if !self.is_reversible() {
+            return Err("Repairs reverse parts of the path, so every move has to be reversible".to_string());
+        }
+        self.repair = true;
+        Ok(self)
+    }
+
+    /// Only makes tours whose grid of move numbers is `magic`, cutting off
+    /// any branch where some row, column or diagonal can no longer add up.
+    /// Repairs would renumber the tour, so they're not allowed with it.
+    pub fn with_magic(mut self, magic: Magic) -> Result<Board, String> {
+        magic.check(&self)?;
+        self.magic = Some(magic);
+        self.reset();
+        Ok(self)
+    }
+
+    /// Only makes closed tours that `symmetry` maps onto themselves. The
+    /// search makes the first half or quarter of the tour, placing the
+    /// images of each square along with it, so it has far fewer squares to
+    /// fill. The board, piece and topology all have to have the symmetry.
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Result<Board, String> {
+        symmetry.check(&self)?;
+        self.symmetry = Some(symmetry);
+        self.reset();
+        Ok(self)
+    }
+
+    /// Looks for the tour with the smallest or largest score by `goal`,
+    /// e.g. the fewest line crossings, cutting off any branch that can't
+    /// beat the best tour so far. Needs a flat 2D board so the lines are
+    /// straight, and a single thread without restarts or symmetry.
+    pub fn with_goal(mut self, goal: Goal) -> Result<Board, String> {
+        if self.shape.dims.len() != 2 || !self.topology.is_plane() {
+            return Err("The optimiser scores lines drawn on a flat 2D board".to_string());
+        }
+        self.goal = Some(goal);
+        Ok(self)
+    }
+
+    /// Looks for the longest path whose lines never cross, Yarbrough's
+    /// problem, rather than for tours. The path starts from every square in
+    /// turn unless there's a prefix, so call this after `with_prefix`.
+    /// Needs a flat 2D board and a single thread, and none of the other
+    /// searches' extras.
+    pub fn with_uncrossed(mut self) -> Result<Board, String> {
+        if self.shape.dims.len() != 2 || !self.topology.is_plane() {
+            return Err("Uncrossed paths are drawn on a flat 2D board".to_string());
+        }
+        uncrossed::check(&self)?;
+        self.uncrossed = true;
+        Ok(self)
+    }
+
+    /// Fails if any two of the options chosen can't go together. The
+    /// `with_` methods only check what they're given against the board, so
+    /// they can be called in any order, and this checks them all at the end.
+    pub fn validate(&self) -> Result<(), String> {
+        let threaded = self.threads > 1;
+        let restarts = self.restart_after.is_some();
+        let limited = self.budget.is_limited();
+        let saving = self.saver.is_some();
+        let magic = self.magic.is_some();
+        let symmetric = self.symmetry.is_some();
+        let goal = self.goal.is_some();
+        let clashes = [
+            (self.bitboard && self.order.bit_order().is_none(), "The bitboard engine only does warnsdorff and lexicographic orders"),
+            (self.bitboard && threaded, "The bitboard engine runs on a single thread"),
+            (self.bitboard && limited, "The bitboard engine doesn't take time, node or rollback limits"),
+            (self.bitboard && saving, "The bitboard engine can't save checkpoints"),
+            (self.bitboard && self.resumed, "The bitboard engine can't resume from a checkpoint"),
+            (self.bitboard && self.repair, "The bitboard engine can't repair open tours"),
+            (self.bitboard && magic, "The bitboard engine can't search for magic tours"),
+            (self.bitboard && symmetric, "The bitboard engine can't search for symmetric tours"),
+            (self.bitboard && goal, "The bitboard engine can't optimise tours"),
+            (self.bitboard && self.uncrossed, "The bitboard engine can't look for uncrossed paths"),
+            (restarts && !self.order.is_random(), "Restarts need the random move order"),
+            (restarts && threaded, "Restarts only work on a single thread"),
+            (limited && threaded, "Limits only work on a single thread"),
+            (saving && threaded, "Checkpoints only work on a single thread"),
+            (self.resumed && threaded, "Searches can only be resumed on a single thread"),
+            (goal && threaded, "The optimiser only works on a single thread"),
+            (self.uncrossed && threaded, "The uncrossed path search only works on a single thread"),
+            (self.repair && self.end.is_some(), "Repairs move the end of the path, so they can't keep an end square"),
+            (self.repair && magic, "Repaired tours are numbered differently, so they can't be kept magic"),
+            (self.repair && symmetric, "Symmetric tours are always closed, so there's nothing to repair"),
+            (self.repair && goal, "The optimiser only scores the tours the search finds, so it can't repair them"),
+            (symmetric && self.end.is_some(), "Symmetric tours are closed, so they can't have an end square"),
+            (goal && restarts, "The optimiser walks the whole search, so it can't restart"),
+            (goal && symmetric, "The optimiser can't search for symmetric tours"),
+            (goal && saving, "The optimiser can't save checkpoints"),
+            (goal && self.resumed, "The optimiser can't resume from a checkpoint"),
+            (self.uncrossed && self.end.is_some(), "The uncrossed path search can't take an end square"),
+            (self.uncrossed && restarts, "The uncrossed path search can't take restarts"),
+            (self.uncrossed && self.repair, "The uncrossed path search can't take repairs"),
+            (self.uncrossed && magic, "The uncrossed path search can't take magic sums"),
+            (self.uncrossed && symmetric, "The uncrossed path search can't take symmetry"),
+            (self.uncrossed && goal, "The uncrossed path search can't take an objective"),
+            (self.uncrossed && saving, "The uncrossed path search can't save checkpoints"),
+            (self.uncrossed && self.resumed, "The uncrossed path search can't resume from a checkpoint"),
+        ];
+        match clashes.iter().find(|c| c.0) {
+            Some((_, why)) => Err(why.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the knight with a piece making `moves`, e.g. from
+    /// `coord::leaper_moves`. Fails for pieces that can never tour on the
+    /// board's shape and topology, so call this after `with_topology`.
+    pub fn with_moves(mut self, moves: Vec<Coord>) -> Result<Board, String> {
+        coord::check_moves(&moves, &self.shape.dims, self.topology.as_ref())?;
+        self.moves = moves;
+        self.reset();
+        Ok(self)
+    }
+
+    /// Throws away any search state and starts again from `start`, which
+    /// counts as the first square visited.
+    fn reset(&mut self) {
+        self.board.iter_mut().for_each(|v| *v = 0);
+        self.mark(self.start, 1);
+        self.moves_made.clear();
+        self.floor = 0;
+        self.visited.clear();
+        self.current = self.start;
+        self.moves_to_make = vec![self.available_moves()];
+        self.rollbacks = 0;
+        self.restarts = 0;
+        self.progress = Progress::default();
+    }
+
+    /// The square reached by making move `m` from `from`, if it's on the board.
+    pub fn step(&self, from: Coord, m: Coord) -> Option<Coord> {
+        self.topology
+            .wrap(from + m, &self.shape.dims)
+            .filter(|&c| self.is_on_board(c))
+    }
+
+    /// The move that takes the knight from `from` to `to`, preferring one
+    /// that doesn't cross a glued edge.
+    pub fn move_between(&self, from: Coord, to: Coord) -> Option<Coord> {
+        let candidates = || {
+            self.moves
+                .iter()
+                .copied()
+                .filter(move |&m| self.step(from, m) == Some(to))
+        };
+        candidates()
+            .find(|&m| from + m == to)
+            .or_else(|| candidates().next())
+    }
+
+    /// Every square `tour` visits, starting square first.
+    pub fn positions(&self, tour: &Tour) -> Vec<Coord> {
+        let mut current = tour.start;
+        std::iter::once(current)
+            .chain(tour.moves.iter().map(|&m| {
+                current = self.step(current, m).expect("Illegal move in tour");
+                current
+            }))
+            .collect()
+    }
+
+    /// The longest path the search has made so far.
+    pub fn best_tour(&self) -> Tour {
+        Tour {
+            start: self.start,
+            moves: self.progress.best.clone(),
+            closed: false,
+            restarts: self.restarts,
+        }
+    }
+
+    /// The tour played so far. A symmetric search that has closed up
+    /// gives the whole tour, images and all.
+    pub fn tour(&self) -> Tour {
+        let closed = self.is_closed_tour();
+        let moves = match self.symmetry {
+            Some(symmetry) if closed && self.is_complete() => {
+                let path = symmetry.expand(&self.positions(&self.partial_tour()), &self.shape.dims);
+                path.iter()
+                    .zip(path.iter().skip(1))
+                    .map(|(&a, &b)| self.move_between(a, b).expect("Symmetric tour is legal"))
+                    .collect()
+            }
+            _ => self.moves_made.clone(),
+        };
+        Tour {
+            start: self.start,
+            moves,
+            closed,
+            restarts: self.restarts,
+        }
+    }
+
+    /// The moves played so far, without any symmetric images.
+    fn partial_tour(&self) -> Tour {
+        Tour {
+            start: self.start,
+            moves: self.moves_made.clone(),
+            closed: false,
+            restarts: self.restarts,
+        }
+    }
+
+    /// Squares a symmetric search has to visit itself, the rest being
+    /// their images. All of them without a symmetry.
+    fn path_len(&self) -> usize {
+        self.square_count() / self.symmetry.map_or(1, |s| s.order())
+    }
+
+    /// True if the piece can undo every move it can make.
+    pub fn is_reversible(&self) -> bool {
+        self.moves.iter().all(|m| {
+            let mut back = *m;
+            back.0.iter_mut().for_each(|x| *x = -*x);
+            self.moves.contains(&back)
+        })
+    }
+
+    /// Number of squares a full tour has to visit, holes excluded.
+    pub fn square_count(&self) -> usize {
+        self.shape.open_count()
+    }
+
+    pub fn is_on_board(&self, c: Coord) -> bool {
+        self.shape.is_open(c)
     }
 
     pub fn can_move(&self, c: Coord) -> bool {
-        self.value_at(c) == 0i8
+        self.value_at(c) == 0
     }
 
     pub fn available_moves(&self) -> Vec<Coord> {
+        if !self.end_reachable() {
+            return Vec::new();
+        }
+        let hopeless = |s: Symmetry| !s.can_start(self, self.start);
+        if self.moves_made.is_empty() && self.symmetry.is_some_and(hopeless) {
+            return Vec::new();
+        }
+        // The end square is saved for the very last move.
+        let last_move = self.moves_made.len() + 2 == self.square_count();
+        let mut targets: Vec<Coord> = Vec::new();
         self.moves
             .iter()
             .copied()
-            .filter(|m| {
-                let c = self.current + m;
-                Board::is_on_board(c) && self.can_move(c)
+            .filter(|&m| match self.step(self.current, m) {
+                Some(c) if self.end.is_some() && (self.end == Some(c)) != last_move => false,
+                Some(c) if self.magic.is_some_and(|magic| !magic::allows(self, c, magic)) => false,
+                // On small wrapped boards two moves can land on the same square.
+                Some(c) if self.can_move(c) && !targets.contains(&c) => {
+                    targets.push(c);
+                    true
+                }
+                _ => false,
             })
             .collect()
     }
 
+    /// With an end square set, some unvisited square has to be left to
+    /// reach it from, otherwise this branch can never finish there.
+    fn end_reachable(&self) -> bool {
+        let end = match self.end {
+            Some(end) if !self.is_complete() => end,
+            _ => return true,
+        };
+        if self.moves_made.len() + 2 == self.square_count() {
+            return true;
+        }
+        self.moves.iter().any(|&m| match self.step(end, m) {
+            Some(c) => c != end && self.can_move(c) && self.move_between(c, end).is_some(),
+            None => false,
+        })
+    }
+
     pub fn make_move(&mut self, c: Coord) {
-        self.current += c;
+        self.current = self.step(self.current, c).expect("Illegal move");
         self.moves_made.push(c);
-        self.set_value_at(self.current, self.moves_made.len() as i8);
+        self.visited.push(self.current);
+        self.mark(self.current, self.moves_made.len() + 1);
     }
 
     pub fn rollback(&mut self) {
-        self.set_value_at(self.current, 0);
-        let rb = self.moves_made.pop().expect("Logic error");
-        self.current -= rb;
+        self.mark(self.current, 0);
+        self.moves_made.pop().expect("Logic error");
+        self.visited.pop();
+        self.current = self.visited.last().copied().unwrap_or(self.start);
     }
 
-    pub fn apply_best_move(&mut self) {
-        //println!("apply board is {:?}", self);
-        //val am = self.available_moves(self);
-        let mut best: Option<(Coord, usize, usize)> = None;
-        for (i, available_move) in self
-            .moves_to_make
-            .last()
-            .unwrap()
-            .clone()
-            .iter()
-            .enumerate()
-        {
-            self.make_move(*available_move);
-            let am = self.available_moves();
-            let new_len = am.len();
-            self.rollback();
-            best = match best {
-                None => Some((*available_move, new_len, i)), // First loop
-                Some((_, best_len, _)) if new_len < best_len => Some((*available_move, new_len, i)), // New best
-                _ => best, // Not a new best - leave as is
+    /// Numbers square `c` with `value`, and its images with theirs in a
+    /// symmetric search. A `value` of 0 clears them all.
+    fn mark(&mut self, c: Coord, value: usize) {
+        self.set_value_at(c, value);
+        if let Some(symmetry) = self.symmetry {
+            for (image, v) in symmetry.images(c, value, self.path_len(), &self.shape.dims) {
+                self.set_value_at(image, if value == 0 { 0 } else { v });
             }
         }
-        assert!(best.is_some());
-        let (c, _, idx) = best.unwrap();
-        self.make_move(c);
+    }
+
+    /// Onward moves there would be after making move `m`.
+    pub fn degree_after(&mut self, m: Coord) -> usize {
+        self.make_move(m);
+        let ret = self.available_moves().len();
+        self.rollback();
+        ret
+    }
+
+    pub fn apply_best_move(&mut self) {
+        //println!("apply board is {:?}", self);
+        let candidates = self.moves_to_make.last().unwrap().clone();
+        let order = self.order.clone();
+        let idx = order.choose(self, &candidates);
+        self.make_move(candidates[idx]);
+        self.progress.nodes += 1;
         self.moves_to_make.last_mut().unwrap().remove(idx);
         self.moves_to_make.push(self.available_moves());
     }
@@ -165,46 +636,571 @@ impl Board {
     }
 
     pub fn is_closed_tour(&self) -> bool {
-        return self
-            .moves
+        if let Some(symmetry) = self.symmetry {
+            return self.is_complete() && symmetry.closes(self, self.start, self.current);
+        }
+        self.moves
             .iter()
-            .any(|m| self.current + m == *(self.moves_made.first().unwrap()));
+            .any(|&m| self.step(self.current, m) == Some(self.start))
+    }
+
+    /// True once every square has been visited, counting the images of
+    /// those visited in a symmetric search.
+    pub fn is_complete(&self) -> bool {
+        self.moves_made.len() + 1 == self.path_len()
     }
 
-    pub fn do_loop(&mut self, sender: Sender<Vec<Coord>>) {
-        loop {
+    /// Walks every branch of the search, calling `on_complete` each time
+    /// every square has been visited, until the branches or the budget run
+    /// out. With restarts the walk may never end.
+    pub fn search<F: FnMut(&Board)>(&mut self, mut on_complete: F) -> Stopped {
+        self.progress.started.get_or_insert_with(Instant::now);
+        // A prefix may already cover the whole board. A search resumed from
+        // a checkpoint has been past here before.
+        if self.is_complete() && self.progress.nodes + self.progress.rollbacks == 0 {
+            self.completed(&mut on_complete);
+        }
+        let stopped = loop {
             let m = self.get_action();
             match m {
                 Mutation::Move => {
                     self.apply_best_move();
-                    if self.moves_made.len() == 64 && self.is_closed_tour() {
-                        sender.send(self.moves_made.clone()).unwrap();
+                    if self.is_complete() {
+                        self.completed(&mut on_complete);
                     }
                 }
                 Mutation::Rollback => {
-                    self.rollback();
+                    // Dead ends are where the paths stop growing.
+                    self.progress.record(&self.moves_made);
+                    self.progress.rollbacks += 1;
+                    // Nothing left to undo once the start square, or the
+                    // end of the prefix, is exhausted.
+                    if self.moves_made.len() > self.floor {
+                        self.rollback();
+                    }
                     self.moves_to_make.pop();
+                    self.rollbacks += 1;
+                    // Once the whole tree has been walked there's nothing
+                    // a different path could find.
+                    let exhausted = self.moves_to_make.is_empty();
+                    if !exhausted && self.restart_after.is_some_and(|n| self.rollbacks >= n) {
+                        self.restart();
+                    }
                 }
                 Mutation::Stop => {
-                    break;
+                    break Stopped::Exhausted;
                 }
             }
+            if let Some(stopped) = self.progress.over(&self.budget) {
+                self.progress.record(&self.moves_made);
+                break stopped;
+            }
+            let due = (self.progress.nodes + self.progress.rollbacks).is_multiple_of(1024);
+            let wanted = |s: &Saver| s.last.elapsed() >= s.every || checkpoint::take_request();
+            if due && self.saver.as_ref().is_some_and(wanted) {
+                self.checkpoint();
+            }
+        };
+        self.checkpoint();
+        stopped
+    }
+
+    /// Counts and reports the tour just completed.
+    fn completed<F: FnMut(&Board)>(&mut self, on_complete: &mut F) {
+        let closed = self.is_closed_tour();
+        // Only a part that closes up makes a tour with its images.
+        if self.symmetry.is_some() && !closed {
+            return;
+        }
+        self.progress.counts.tally(closed);
+        on_complete(self);
+        let mut sent = self.report.wants(closed);
+        if sent {
+            self.restarts = 0;
+        }
+        if self.repair && !closed && self.report.wants(true) {
+            if let Ok(tour) = repair::close(self, &self.tour(), self.floor) {
+                let mut fixed = self
+                    .clone()
+                    .with_prefix(&self.positions(&tour))
+                    .expect("Repaired tour is legal");
+                fixed.restarts = self.restarts;
+                on_complete(&fixed);
+                self.restarts = 0;
+                sent = true;
+            }
         }
+        if sent && self.saver.as_ref().is_some_and(|s| s.after_tours) {
+            self.checkpoint();
+        }
+    }
+
+    /// Saves the search state, if checkpoints were asked for. A failed
+    /// save is reported but doesn't stop the search.
+    fn checkpoint(&mut self) {
+        if let Some(saver) = &self.saver {
+            if let Err(e) = checkpoint::save(self, &saver.path) {
+                eprintln!("Couldn't save a checkpoint: {}", e);
+            }
+        }
+        if let Some(saver) = &mut self.saver {
+            saver.last = Instant::now();
+        }
+    }
+
+    /// Backs out to the prefix and starts the search from there afresh.
+    fn restart(&mut self) {
+        while self.moves_made.len() > self.floor {
+            self.rollback();
+        }
+        self.pin_prefix();
+        self.rollbacks = 0;
+        self.restarts += 1;
+    }
+
+    /// Sends every tour of the kind asked for, returning how many were sent
+    /// and why the search stopped.
+    pub fn do_loop(&mut self, sender: Sender<Tour>) -> (usize, Stopped) {
+        if self.bitboard {
+            return (bitboard::do_loop(self, &sender), Stopped::Exhausted);
+        }
+        if self.uncrossed {
+            let mut found = 0;
+            let stopped = uncrossed::search(self, |b| {
+                sender.send(uncrossed::path(b)).unwrap();
+                found += 1;
+            });
+            return (found, stopped);
+        }
+        if self.goal.is_some() {
+            let mut found = 0;
+            let stopped = optimise::search(self, |b, _| {
+                sender.send(b.tour()).unwrap();
+                found += 1;
+            });
+            return (found, stopped);
+        }
+        if self.threads > 1 {
+            let report = |found: &mut usize, b: &Board| {
+                if b.report.wants(b.is_closed_tour()) {
+                    sender.send(b.tour()).unwrap();
+                    *found += 1;
+                }
+            };
+            return (parallel::search(self, report).into_iter().sum(), Stopped::Exhausted);
+        }
+        if let Some(saver) = &mut self.saver {
+            saver.after_tours = true;
+        }
+        let mut found = 0;
+        let stopped = self.search(|b| {
+            if b.report.wants(b.is_closed_tour()) {
+                sender.send(b.tour()).unwrap();
+                found += 1;
+            }
+        });
+        (found, stopped)
     }
 }
 
 
 
+/// Command line options, e.g. `cargo run -- --size 5x6`, `--size 4x4x4` or
+/// `cargo run -- --shape cross.txt --topology torus --leaper 1,4`.
+struct Options {
+    dims: Vec<i16>,
+    shape_file: Option<String>,
+    topology: Arc<dyn Topology>,
+    leaper: Option<(i16, i16)>,
+    moves: Option<Vec<Coord>>,
+    piece: Option<String>,
+    start: Option<Coord>,
+    end: Option<Coord>,
+    prefix: Option<Vec<Coord>>,
+    report: Report,
+    order: Arc<dyn MoveOrder>,
+    seed: u64,
+    count: bool,
+    threads: usize,
+    split_depth: usize,
+    bitboard: bool,
+    construct: bool,
+    neural: Option<usize>,
+    cnf: Option<String>,
+    model: Option<String>,
+    sat: bool,
+    restarts: Option<usize>,
+    budget: Budget,
+    checkpoint: Option<String>,
+    checkpoint_every: Duration,
+    resume: Option<String>,
+    repair: bool,
+    magic: Option<Magic>,
+    symmetry: Option<Symmetry>,
+    goal: Option<Goal>,
+    uncrossed: bool,
+}
+
+impl Options {
+    /// True if something other than the backtracking search was asked for.
+    fn other_engine(&self) -> bool {
+        self.construct || self.neural.is_some() || self.cnf.is_some() || self.model.is_some() || self.sat
+    }
+
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut ret = Options {
+            dims: vec![8, 8],
+            shape_file: None,
+            topology: Arc::new(topology::Plane),
+            leaper: None,
+            moves: None,
+            piece: None,
+            start: None,
+            end: None,
+            prefix: None,
+            report: Report::Closed,
+            order: Arc::new(order::Warnsdorff),
+            seed: 0,
+            count: false,
+            threads: 1,
+            split_depth: 4,
+            bitboard: false,
+            construct: false,
+            neural: None,
+            cnf: None,
+            model: None,
+            sat: false,
+            restarts: None,
+            budget: Budget::default(),
+            checkpoint: None,
+            checkpoint_every: Duration::from_secs(60),
+            resume: None,
+            repair: false,
+            magic: None,
+            symmetry: None,
+            goal: None,
+            uncrossed: false,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => {
+                    let v = args.next().ok_or("--size needs a value like 5x6")?;
+                    ret.dims = v
+                        .split('x')
+                        .map(|d| d.parse().map_err(|_| format!("Bad dimension '{}'", d)))
+                        .collect::<Result<_, String>>()?;
+                    if !(2..=coord::MAX_DIMS).contains(&ret.dims.len()) {
+                        return Err(format!(
+                            "Bad size '{}', expected 2 to {} dimensions like WxH or WxHxD",
+                            v,
+                            coord::MAX_DIMS
+                        ));
+                    }
+                    if ret.dims.iter().any(|&d| d <= 0) {
+                        return Err(format!("Bad size '{}', dimensions must be positive", v));
+                    }
+                }
+                "--shape" => {
+                    ret.shape_file = Some(args.next().ok_or("--shape needs a file name")?);
+                }
+                "--leaper" => {
+                    let v = args.next().ok_or("--leaper needs a value like 1,3")?;
+                    match parse_ints(&v)?[..] {
+                        [a, b] => ret.leaper = Some((a, b)),
+                        _ => return Err(format!("Bad leaper '{}', expected a,b", v)),
+                    }
+                }
+                "--moves" => {
+                    let v = args.next().ok_or("--moves needs a value like 1,2;2,1")?;
+                    let moves = v
+                        .split(';')
+                        .map(|m| parse_ints(m).map(|xs| Coord::new(&xs)))
+                        .collect::<Result<_, _>>()?;
+                    ret.moves = Some(moves);
+                }
+                "--piece" => {
+                    ret.piece = Some(args.next().ok_or("--piece needs a name like knight+camel")?);
+                }
+                "--start" => {
+                    let v = args.next().ok_or("--start needs a square like 3,4")?;
+                    ret.start = Some(Coord::new(&parse_ints(&v)?));
+                }
+                "--end" => {
+                    let v = args.next().ok_or("--end needs a square like 3,4")?;
+                    ret.end = Some(Coord::new(&parse_ints(&v)?));
+                }
+                "--prefix" => {
+                    let v = args.next().ok_or("--prefix needs squares like 0,0;1,2;2,4")?;
+                    let path = v
+                        .split(';')
+                        .map(|c| parse_ints(c).map(|xs| Coord::new(&xs)))
+                        .collect::<Result<_, _>>()?;
+                    ret.prefix = Some(path);
+                }
+                "--report" => {
+                    let v = args.next().ok_or("--report needs closed, open or all")?;
+                    ret.report = Report::by_name(&v).ok_or_else(|| {
+                        format!("Unknown report '{}', expected closed, open or all", v)
+                    })?;
+                }
+                "--order" => {
+                    let v = args.next().ok_or("--order needs a heuristic like pohl")?;
+                    ret.order = order::by_name(&v)?;
+                }
+                "--seed" => {
+                    let v = args.next().ok_or("--seed needs a number")?;
+                    ret.seed = v.parse().map_err(|_| format!("Bad seed '{}'", v))?;
+                }
+                "--count" => ret.count = true,
+                "--bitboard" => ret.bitboard = true,
+                "--construct" => ret.construct = true,
+                "--neural" => {
+                    let v = args.next().ok_or("--neural needs a number of runs")?;
+                    ret.neural = Some(v.parse().map_err(|_| format!("Bad number of runs '{}'", v))?);
+                }
+                "--cnf" => {
+                    ret.cnf = Some(args.next().ok_or("--cnf needs a file name")?);
+                }
+                "--model" => {
+                    ret.model = Some(args.next().ok_or("--model needs a file name")?);
+                }
+                "--sat" => ret.sat = true,
+                "--repair" => ret.repair = true,
+                "--uncrossed" => ret.uncrossed = true,
+                "--magic" => {
+                    let v = args.next().ok_or("--magic needs semi or full")?;
+                    let magic = Magic::by_name(&v).ok_or_else(|| format!("Unknown magic '{}', expected semi or full", v))?;
+                    ret.magic = Some(magic);
+                }
+                "--symmetry" => {
+                    let v = args.next().ok_or("--symmetry needs 180, 90 or mirror")?;
+                    let symmetry = Symmetry::by_name(&v)
+                        .ok_or_else(|| format!("Unknown symmetry '{}', expected 180, 90 or mirror", v))?;
+                    ret.symmetry = Some(symmetry);
+                }
+                "--minimise" | "--maximise" => {
+                    let v = args.next().ok_or_else(|| format!("{} needs crossings or length", arg))?;
+                    ret.goal = Some(Goal {
+                        most: arg == "--maximise",
+                        objective: optimise::by_name(&v)?,
+                    });
+                }
+                "--restarts" => {
+                    let v = args.next().ok_or("--restarts needs a number of rollbacks")?;
+                    ret.restarts = Some(v.parse().map_err(|_| format!("Bad number of rollbacks '{}'", v))?);
+                }
+                "--time" => {
+                    let v = args.next().ok_or("--time needs a number of seconds")?;
+                    let secs: f64 = v.parse().map_err(|_| format!("Bad number of seconds '{}'", v))?;
+                    let time = Duration::try_from_secs_f64(secs).map_err(|e| format!("Bad time '{}': {}", v, e))?;
+                    ret.budget.time = Some(time);
+                }
+                "--nodes" => {
+                    let v = args.next().ok_or("--nodes needs a number")?;
+                    ret.budget.nodes = Some(v.parse().map_err(|_| format!("Bad node limit '{}'", v))?);
+                }
+                "--rollbacks" => {
+                    let v = args.next().ok_or("--rollbacks needs a number")?;
+                    ret.budget.rollbacks = Some(v.parse().map_err(|_| format!("Bad rollback limit '{}'", v))?);
+                }
+                "--checkpoint" => {
+                    ret.checkpoint = Some(args.next().ok_or("--checkpoint needs a file name")?);
+                }
+                "--checkpoint-every" => {
+                    let v = args.next().ok_or("--checkpoint-every needs a number of seconds")?;
+                    let secs: f64 = v.parse().map_err(|_| format!("Bad number of seconds '{}'", v))?;
+                    ret.checkpoint_every =
+                        Duration::try_from_secs_f64(secs).map_err(|e| format!("Bad time '{}': {}", v, e))?;
+                }
+                "--resume" => {
+                    ret.resume = Some(args.next().ok_or("--resume needs a checkpoint file")?);
+                }
+                "--threads" => {
+                    let v = args.next().ok_or("--threads needs a number")?;
+                    ret.threads = v.parse().map_err(|_| format!("Bad thread count '{}'", v))?;
+                }
+                "--split-depth" => {
+                    let v = args.next().ok_or("--split-depth needs a number")?;
+                    ret.split_depth = v.parse().map_err(|_| format!("Bad split depth '{}'", v))?;
+                }
+                "--topology" => {
+                    let v = args.next().ok_or("--topology needs a name")?;
+                    ret.topology = topology::by_name(&v).ok_or_else(|| {
+                        format!(
+                            "Unknown topology '{}', expected plane, cylinder, torus, mobius or klein",
+                            v
+                        )
+                    })?;
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+fn parse_ints(v: &str) -> Result<Vec<i16>, String> {
+    let ret: Vec<i16> = v
+        .split(',')
+        .map(|x| x.trim().parse().map_err(|_| format!("Bad number '{}'", x)))
+        .collect::<Result<_, _>>()?;
+    if ret.len() > coord::MAX_DIMS {
+        return Err(format!("'{}' has more than {} dimensions", v, coord::MAX_DIMS));
+    }
+    Ok(ret)
+}
+
 fn main()  {
     //my_serde::main();
 
     //println!("{} days", experiment::mysum(1, 2));
     //println!("{} days", experiment::mysum(1.0, 2.0));
 
-    doit().expect("TODO: panic message");
+    let options = Options::from_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let result = build_board(&options).and_then(|b| {
+        if options.count && options.budget.is_limited() {
+            Err("--count walks the whole search, so it can't take --time, --nodes or --rollbacks".to_string())
+        } else if b.goal.is_some() && (options.count || options.other_engine()) {
+            Err("--minimise and --maximise only work with the backtracking search".to_string())
+        } else if b.uncrossed && (options.count || options.other_engine()) {
+            Err("--uncrossed is a search of its own, so it can't take --count or another engine".to_string())
+        } else if options.count {
+            let one_start = options.start.is_some() || options.prefix.is_some();
+            count::print_report(&b, &count::by_start(&b, one_start));
+            Ok(())
+        } else if b.symmetry.is_some() && options.other_engine() {
+            Err("--symmetry only works with the backtracking search".to_string())
+        } else if b.magic.is_some() && options.other_engine() {
+            Err("--magic only works with the backtracking search".to_string())
+        } else if options.construct {
+            print_constructed(&b)
+        } else if let Some(runs) = options.neural {
+            if b.end.is_some() || b.floor > 0 {
+                return Err("--neural only looks for closed tours, so it can't take --end or --prefix".to_string());
+            }
+            neural::print_runs(&b, runs);
+            Ok(())
+        } else if let Some(path) = &options.cnf {
+            sat::write_dimacs(&b, path)
+        } else if let Some(path) = &options.model {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let model = sat::read_model(&text).map_err(|e| format!("{}: {}", path, e))?;
+            print_squares(&b, &sat::decode(&b, &model)?);
+            Ok(())
+        } else if options.sat {
+            let model = sat::solve(&sat::encode(&b)).ok_or("No tour meets the constraints")?;
+            print_squares(&b, &sat::decode(&b, &model)?);
+            Ok(())
+        } else {
+            doit(b)
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
-fn doit() -> Result<(), String> {
+fn build_board(options: &Options) -> Result<Board, String> {
+    let mut b = match &options.shape_file {
+        Some(path) => Board::with_shape(Shape::load(path)?),
+        None => Board::new(&options.dims),
+    }
+    .with_topology(options.topology.clone());
+    if let Some((x, y)) = options.leaper {
+        let moves = coord::leaper_moves(x, y, b.shape.dims.len())?;
+        b = b.with_moves(moves)?;
+    }
+    if let Some(moves) = &options.moves {
+        b = b.with_moves(moves.clone())?;
+    }
+    if let Some(name) = &options.piece {
+        let moves = piece::moves_by_name(name, b.shape.dims.len())?;
+        b = b.with_moves(moves)?;
+    }
+    if let Some(start) = options.start {
+        b = b.with_start(start)?;
+    }
+    if let Some(end) = options.end {
+        b = b.with_end(end)?;
+    }
+    // Counting walks every branch whatever the order, so it always goes
+    // in the cheapest one, which is also what its checkpoints record.
+    let order = if options.count {
+        Arc::new(order::Lexicographic)
+    } else {
+        options.order.clone()
+    };
+    b = b
+        .with_report(options.report)
+        .with_order(order)
+        .with_seed(options.seed)
+        .with_threads(options.threads, options.split_depth);
+    if let Some(rollbacks) = options.restarts {
+        b = b.with_restarts(rollbacks)?;
+    }
+    if options.budget.is_limited() {
+        b = b.with_budget(options.budget);
+    }
+    if let Some(magic) = options.magic {
+        b = b.with_magic(magic)?;
+    }
+    if let Some(symmetry) = options.symmetry {
+        b = b.with_symmetry(symmetry)?;
+    }
+    if let Some(goal) = &options.goal {
+        b = b.with_goal(goal.clone())?;
+    }
+    if let Some(path) = &options.prefix {
+        b = b.with_prefix(path)?;
+    }
+    if options.repair {
+        b = b.with_repair()?;
+    }
+    if options.uncrossed {
+        b = b.with_uncrossed()?;
+    }
+    if let Some(path) = &options.checkpoint {
+        checkpoint::listen_for_signal();
+        b = b.with_checkpoints(path, options.checkpoint_every);
+    }
+    if options.bitboard {
+        b = b.with_bitboard()?;
+    }
+    if let Some(path) = &options.resume {
+        b = b.with_resume(checkpoint::load(path)?)?;
+    }
+    b.validate()?;
+    Ok(b)
+}
+
+/// Prints a constructed closed tour of `b`'s rectangle, one square per line.
+fn print_constructed(b: &Board) -> Result<(), String> {
+    let plain = b.shape.dims.len() == 2
+        && b.square_count() == b.shape.len()
+        && b.moves == coord::knight_moves(2);
+    if !plain {
+        return Err("--construct only builds knight's tours of plain rectangles".to_string());
+    }
+    let tour = construct::closed_tour(b.shape.width(), b.shape.height())?;
+    print_squares(b, &b.positions(&tour));
+    Ok(())
+}
+
+/// Prints the squares of a tour in order, one per line, e.g. `3,4`.
+fn print_squares(b: &Board, squares: &[Coord]) {
+    for c in squares {
+        let xs: Vec<String> = c.0[..b.shape.dims.len()].iter().map(|x| x.to_string()).collect();
+        println!("{}", xs.join(","));
+    }
+}
+
+fn doit(mut b: Board) -> Result<(), String> {
+    let view = b.clone();
+    let shape = b.shape.clone();
     let sdl_context = sdl2::init()?;
     //let ev = sdl_context.event().unwrap();
     let video_subsystem = sdl_context.video()?;
@@ -223,16 +1219,49 @@ fn doit() -> Result<(), String> {
 
     //let( a, b) = mpsc::channel();
         
-    let mut b = Board::new();
+    let (width, height) = (shape.width() as i32, shape.height() as i32);
+    // Boards with more than two axes are drawn as a row of 2D slices, one
+    // per layer, with a gap of one square between them.
+    let layers: i32 = shape.dims[2..].iter().map(|&d| d as i32).product();
+    let layer_of = |c: Coord| {
+        (2..shape.dims.len()).fold(0, |acc, axis| acc * shape.dims[axis] as i32 + c[axis] as i32)
+    };
 
     std::thread::spawn(move || {
-        b.do_loop(tx);
+        let (found, stopped) = b.do_loop(tx);
+        if b.budget.is_limited() {
+            budget::print_report(&b, found, stopped);
+        } else if found == 0 {
+            eprintln!("Search finished without finding a tour");
+        }
+        if (b.goal.is_some() || b.uncrossed) && found > 0 && stopped == Stopped::Exhausted {
+            println!("Every branch was searched, so tour {} is the best there is", found);
+        }
     });
 
-    let mut current_vec: Option<Vec<Coord>> = None;
+    let mut current_tour: Option<Tour> = None;
+    let mut found = 0;
     'mainloop: loop {
-        if let Ok(vec) = rx.try_recv() {
-            current_vec = Some(vec);
+        if let Ok(tour) = rx.try_recv() {
+            found += 1;
+            let mut notes = Vec::new();
+            if view.restart_after.is_some() {
+                notes.push(format!("took {} restarts", tour.restarts));
+            }
+            if let Some(magic) = magic::kind(&view, &tour) {
+                notes.push(format!("is {}", magic));
+            }
+            if view.uncrossed {
+                notes.push(format!("has {} moves without crossing", tour.moves.len()));
+            }
+            if let Some(goal) = &view.goal {
+                let objective = goal.objective.as_ref();
+                notes.push(objective.describe(optimise::score(&view, objective, &tour)));
+            }
+            if !notes.is_empty() {
+                println!("Tour {} {}", found, notes.join(" and "));
+            }
+            current_tour = Some(tour);
             // ev.push_event(sdl2::event::Event::User {
             //     timestamp: 0,
             //     window_id: 0,
@@ -250,64 +1279,80 @@ fn doit() -> Result<(), String> {
                     ..
                 }
                 | Event::Quit { .. } => break 'mainloop,
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    ..
+                } if view.saver.is_some() => checkpoint::request(),
                 _ => {}
             }
         }
 
+        canvas.set_clip_rect(None);
         canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
         canvas.clear();
-        const SZ: i32 = 90;
-        canvas.set_draw_color(Color::RGBA(255, 255, 255, 255));
-        for x in 0i32..8 {
-            for y in 0i32..8 {
-                if (x + y) % 2 == 0 {
-                    canvas.fill_rect(Rect::new(x * SZ, y * SZ, SZ as u32, SZ as u32))?
-                }
-            }
+        // Keep the board inside the window whatever its shape.
+        let sz: i32 = (720 / (layers * (width + 1) - 1).max(height)).max(1);
+        let slice = |layer: i32| {
+            Rect::new(layer * (width + 1) * sz, 0, (width * sz) as u32, (height * sz) as u32)
+        };
+        for c in (0..shape.len()).map(|i| shape.coord_of(i)) {
+            let (x, y) = (c.x() as i32, c.y() as i32);
+            let colour = if !shape.is_open(c) {
+                Color::RGBA(80, 80, 80, 255)
+            } else if (x + y) % 2 == 0 {
+                Color::RGBA(255, 255, 255, 255)
+            } else {
+                continue;
+            };
+            let origin = slice(layer_of(c)).x();
+            canvas.set_draw_color(colour);
+            canvas.fill_rect(Rect::new(origin + x * sz, y * sz, sz as u32, sz as u32))?
         }
 
-        // const CIRCLE_RADIUS: i16 = 40; //i16;
         let red = Color::RGBA(255, 0, 0, 255);
-        // let green = Color::RGBA(0, 255, 0, 255);
-        // let blue = Color::RGBA(0, 0, 255, 255);
-        if let Some(xs) = &current_vec {
-            let mut current = Coord(0, 0);
-            let mut last: Option<Point> = None;
-            let mut first: Option<Point> = None;
-            for &x in xs.iter() {
-                current += x;
-                let c = &current;
-                let new = Point::new(c.0 as i32 * SZ + SZ / 2, c.1 as i32 * SZ + SZ / 2);
-
-                if first.is_none() {
-                    first = Some(new)
-                }
-
-                if let Some(l) = last {
-                    canvas
-                        .thick_line(l.x as i16, l.y as i16, new.x as i16, new.y as i16, 12, red)
-                        .unwrap()
-                };
-                // canvas
-                // .filled_circle(new.x as i16, new.y as i16, CIRCLE_RADIUS, green)
-                // .unwrap(),
-                last = Some(new)
+        let blue = Color::RGBA(0, 0, 255, 255);
+        let thickness = (sz / 8).max(1) as u8;
+        let green = Color::RGBA(0, 255, 0, 255);
+        if let Some(tour) = &current_tour {
+            let positions = view.positions(tour);
+            // Off-board points are drawn relative to the slice they are next to.
+            let centre = |c: Coord, layer: i32| {
+                let origin = slice(layer).x();
+                Point::new(origin + c.x() as i32 * sz + sz / 2, c.y() as i32 * sz + sz / 2)
+            };
+            // Last pair is the closing move back to the first square.
+            let closing = positions
+                .last()
+                .into_iter()
+                .zip(positions.first())
+                .filter(|_| tour.closed);
+            for (&a, &b) in positions.iter().zip(positions.iter().skip(1)).chain(closing) {
+                let (la, lb) = (layer_of(a), layer_of(b));
+                let m = view.move_between(a, b).expect("Not a legal move");
+                let mut segments = Vec::new();
+                if a + m == b {
+                    segments.push((centre(a, la), centre(b, lb), None));
+                } else {
+                    // Crosses a glued edge, so draw the half leaving the board
+                    // and the half arriving; the clip rect hides the overhang.
+                    let back = view.move_between(b, a).expect("Not a legal move");
+                    segments.push((centre(a, la), centre(a + m, la), Some(slice(la))));
+                    segments.push((centre(b + back, lb), centre(b, lb), Some(slice(lb))));
+                }
+                // Moves between layers are blue.
+                let colour = if la == lb { red } else { blue };
+                for (f, t, clip) in segments {
+                    canvas.set_clip_rect(clip);
+                    canvas.thick_line(f.x as i16, f.y as i16, t.x as i16, t.y as i16, thickness, colour)?;
+                }
             }
-
-            /*            if let Some(last_point) = last {
-                canvas
-                    .filled_circle(
-                        last_point.x as i16,
-                        last_point.y as i16,
-                        CIRCLE_RADIUS,
-                        blue,
-                    )
-                    .unwrap();
-            } */
-            if let (Some(f), Some(l)) = (first, last) {
-                canvas
-                    .thick_line(f.x as i16, f.y as i16, l.x as i16, l.y as i16, 12, red)
-                    .unwrap()
+            // Open tours get a green dot at each end.
+            if !tour.closed {
+                canvas.set_clip_rect(None);
+                for &c in positions.first().into_iter().chain(positions.last()) {
+                    let p = centre(c, layer_of(c));
+                    canvas.filled_circle(p.x as i16, p.y as i16, (sz / 4) as i16, green)?;
+                }
             }
         }
         canvas.present();
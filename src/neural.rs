@@ -0,0 +1,207 @@
+use crate::coord::Coord;
+use crate::rng::Rng;
+use crate::tour::Tour;
+use crate::Board;
+
+/// Updates the network gets before a run is given up as unsettled.
+pub const MAX_UPDATES: usize = 1000;
+
+/// How one run of the network ended.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// Every square has two chosen moves and they form one cycle.
+    Tour(Tour),
+    /// Every square has two chosen moves, but they form several cycles,
+    /// longest first.
+    Split(Vec<usize>),
+    /// Some square still didn't have exactly two chosen moves after
+    /// `MAX_UPDATES` updates.
+    Unsettled,
+}
+
+/// The Takefuji–Lee network: one neuron for each pair of squares a move
+/// apart, which fires if that move is part of the answer. Each update
+/// a neuron's input goes up by 4 less the number of firing neurons at
+/// each of its two squares, so it stops changing once every square has
+/// exactly two moves in.
+///
+/// Output switches on above 3 and off below 0 and otherwise stays as it
+/// was, which stops the network flipping back and forth. All neurons
+/// update at once, as in the original, so some runs never settle.
+#[derive(Debug)]
+pub struct Network {
+    squares: Vec<Coord>,
+    /// Pairs of indexes into `squares`.
+    edges: Vec<(usize, usize)>,
+    /// Edges touching each square.
+    touching: Vec<Vec<usize>>,
+}
+
+impl Network {
+    /// The network for `board`'s squares and moves. Only moves that can be
+    /// made both ways count, as a cycle may go round either way.
+    pub fn new(board: &Board) -> Network {
+        let squares: Vec<Coord> = board.shape.squares().collect();
+        let index = |c: Coord| {
+            squares.binary_search_by_key(&board.shape.index_of(c), |&s| board.shape.index_of(s))
+        };
+        let mut edges = Vec::new();
+        let mut touching = vec![Vec::new(); squares.len()];
+        for (i, &a) in squares.iter().enumerate() {
+            // On small wrapped boards two moves can land on the same square.
+            let mut targets = Vec::new();
+            for &m in &board.moves {
+                let j = match board.step(a, m).map(index) {
+                    Some(Ok(j)) if j > i => j,
+                    _ => continue,
+                };
+                let b = squares[j];
+                if targets.contains(&j) || board.move_between(b, a).is_none() {
+                    continue;
+                }
+                targets.push(j);
+                touching[i].push(edges.len());
+                touching[j].push(edges.len());
+                edges.push((i, j));
+            }
+        }
+        Network {
+            squares,
+            edges,
+            touching,
+        }
+    }
+
+    /// Runs the network from random inputs and outputs until it settles
+    /// or `MAX_UPDATES` updates have gone by. A tour found is reported
+    /// from `board`'s start square.
+    pub fn run(&self, board: &Board, rng: &mut Rng) -> Outcome {
+        let mut input: Vec<i32> = self.edges.iter().map(|_| rng.below(4) as i32).collect();
+        let mut output: Vec<bool> = self.edges.iter().map(|_| rng.below(2) == 1).collect();
+        for _ in 0..MAX_UPDATES {
+            let degree: Vec<i32> = self
+                .touching
+                .iter()
+                .map(|es| es.iter().filter(|&&e| output[e]).count() as i32)
+                .collect();
+            if degree.iter().all(|&d| d == 2) {
+                return self.outcome(board, &output);
+            }
+            // Every neuron is updated at once from the previous outputs.
+            for (e, &(i, j)) in self.edges.iter().enumerate() {
+                input[e] += 4 - degree[i] - degree[j];
+                if input[e] > 3 {
+                    output[e] = true;
+                } else if input[e] < 0 {
+                    output[e] = false;
+                }
+            }
+        }
+        Outcome::Unsettled
+    }
+
+    /// Splits the chosen moves, two at each square, into cycles.
+    fn outcome(&self, board: &Board, output: &[bool]) -> Outcome {
+        let next: Vec<Vec<usize>> = self
+            .touching
+            .iter()
+            .enumerate()
+            .map(|(i, es)| {
+                es.iter()
+                    .filter(|&&e| output[e])
+                    .map(|&e| {
+                        if self.edges[e].0 == i {
+                            self.edges[e].1
+                        } else {
+                            self.edges[e].0
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut seen = vec![false; self.squares.len()];
+        let mut cycles: Vec<Vec<usize>> = Vec::new();
+        for first in 0..self.squares.len() {
+            if seen[first] {
+                continue;
+            }
+            let mut cycle = vec![first];
+            seen[first] = true;
+            let (mut prev, mut current) = (first, next[first][0]);
+            while current != first {
+                cycle.push(current);
+                seen[current] = true;
+                let onward = if next[current][0] != prev {
+                    next[current][0]
+                } else {
+                    next[current][1]
+                };
+                prev = current;
+                current = onward;
+            }
+            cycles.push(cycle);
+        }
+        if cycles.len() > 1 {
+            let mut lengths: Vec<usize> = cycles.iter().map(|c| c.len()).collect();
+            lengths.sort_unstable_by(|a, b| b.cmp(a));
+            return Outcome::Split(lengths);
+        }
+        let mut cycle = cycles.pop().unwrap();
+        let from = cycle
+            .iter()
+            .position(|&i| self.squares[i] == board.start)
+            .unwrap_or(0);
+        cycle.rotate_left(from);
+        let squares: Vec<Coord> = cycle.iter().map(|&i| self.squares[i]).collect();
+        let moves = squares
+            .iter()
+            .zip(squares.iter().skip(1))
+            .map(|(&a, &b)| board.move_between(a, b).expect("Not a legal move"))
+            .collect();
+        Outcome::Tour(Tour {
+            start: squares[0],
+            moves,
+            closed: true,
+            restarts: 0,
+        })
+    }
+}
+
+/// Runs the network `runs` times from `board`'s seed onwards, printing how
+/// each run ended and how often each outcome came up.
+pub fn print_runs(board: &Board, runs: usize) {
+    let network = Network::new(board);
+    let mut rng = board.rng.clone();
+    let (mut tours, mut splits, mut unsettled) = (0, 0, 0);
+    for run in 1..=runs {
+        match network.run(board, &mut rng) {
+            Outcome::Tour(tour) => {
+                tours += 1;
+                let squares: Vec<String> = board
+                    .positions(&tour)
+                    .into_iter()
+                    .map(|c| board.show(c))
+                    .collect();
+                println!("run {}: closed tour {}", run, squares.join(" "));
+            }
+            Outcome::Split(lengths) => {
+                splits += 1;
+                let lengths: Vec<String> = lengths.iter().map(|l| l.to_string()).collect();
+                println!(
+                    "run {}: split into {} cycles of {} squares",
+                    run,
+                    lengths.len(),
+                    lengths.join(", ")
+                );
+            }
+            Outcome::Unsettled => {
+                unsettled += 1;
+                println!("run {}: didn't settle in {} updates", run, MAX_UPDATES);
+            }
+        }
+    }
+    println!(
+        "{} runs: {} closed tours, {} split into several cycles, {} unsettled",
+        runs, tours, splits, unsettled
+    );
+}
@@ -0,0 +1,207 @@
+use crate::budget::Stopped;
+use crate::coord::Coord;
+use crate::geometry;
+use crate::tour::Tour;
+use crate::{Board, Mutation};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A score of a tour as drawn, one straight line from square to square.
+/// It's a sum over the lines, so the optimiser can add each one as the
+/// search draws it and cut off branches that can't beat the best so far.
+pub trait Objective: Debug + Send + Sync {
+    /// What the line from the last square of `path` to `to` adds.
+    fn added(&self, path: &[Coord], to: Coord) -> f64;
+
+    /// The least and the most `left` more lines could add to `path` on
+    /// `board`. Looser bounds are fine, they only prune less.
+    fn range(&self, board: &Board, path: &[Coord], left: usize) -> (f64, f64);
+
+    /// Describes a tour's score, e.g. "has 12 crossings".
+    fn describe(&self, score: f64) -> String;
+}
+
+/// Pairs of lines crossing each other, touching included.
+#[derive(Debug)]
+pub struct Crossings;
+
+impl Objective for Crossings {
+    fn added(&self, path: &[Coord], to: Coord) -> f64 {
+        geometry::crossings(path, *path.last().unwrap(), to) as f64
+    }
+
+    fn range(&self, _board: &Board, path: &[Coord], left: usize) -> (f64, f64) {
+        // The line drawn k-th can cross every earlier one but the last.
+        let drawn = path.len() - 1;
+        let most = (drawn..drawn + left)
+            .map(|k| k.saturating_sub(1))
+            .sum::<usize>();
+        (0.0, most as f64)
+    }
+
+    fn describe(&self, score: f64) -> String {
+        format!("has {} crossings", score)
+    }
+}
+
+/// Total length of the lines. Every knight move is √5 long, so this only
+/// varies for pieces with moves of different lengths.
+#[derive(Debug)]
+pub struct Length;
+
+impl Objective for Length {
+    fn added(&self, path: &[Coord], to: Coord) -> f64 {
+        geometry::length(*path.last().unwrap(), to)
+    }
+
+    fn range(&self, board: &Board, _path: &[Coord], left: usize) -> (f64, f64) {
+        let lengths = board
+            .moves
+            .iter()
+            .map(|&m| geometry::length(Coord::default(), m));
+        let (shortest, longest) =
+            lengths.fold((f64::MAX, 0.0f64), |(s, l), x| (s.min(x), l.max(x)));
+        (shortest * left as f64, longest * left as f64)
+    }
+
+    fn describe(&self, score: f64) -> String {
+        format!("is {:.3} long", score)
+    }
+}
+
+pub fn by_name(name: &str) -> Result<Arc<dyn Objective>, String> {
+    match name {
+        "crossings" => Ok(Arc::new(Crossings)),
+        "length" => Ok(Arc::new(Length)),
+        _ => Err(format!(
+            "Unknown objective '{}', expected crossings or length",
+            name
+        )),
+    }
+}
+
+/// What to make of an objective.
+#[derive(Debug, Clone)]
+pub struct Goal {
+    /// Look for the largest score rather than the smallest.
+    pub most: bool,
+    pub objective: Arc<dyn Objective>,
+}
+
+impl Goal {
+    fn better(&self, score: f64, than: Option<f64>) -> bool {
+        match than {
+            None => true,
+            Some(best) if self.most => score > best,
+            Some(best) => score < best,
+        }
+    }
+}
+
+/// The score of `tour` on `board`, its closing line included.
+pub fn score(board: &Board, objective: &dyn Objective, tour: &Tour) -> f64 {
+    let mut path = board.positions(tour);
+    if tour.closed {
+        path.push(path[0]);
+    }
+    (1..path.len())
+        .map(|i| objective.added(&path[..i], path[i]))
+        .sum()
+}
+
+/// Branch and bound over the tours `board` reports, calling `on_better`
+/// with each one that beats every tour before it. Once every branch has
+/// been walked the last tour sent is the best there is; the budget can
+/// stop the search before that.
+pub fn search<F: FnMut(&Board, f64)>(board: &mut Board, mut on_better: F) -> Stopped {
+    let goal = board.goal.clone().expect("Nothing to optimise");
+    let objective = goal.objective.as_ref();
+    board.progress.started.get_or_insert_with(Instant::now);
+    let mut path = board.positions(&board.tour());
+    // The score of the path up to each square, the prefix included.
+    let mut scores = vec![0.0];
+    for i in 1..path.len() {
+        scores.push(scores[i - 1] + objective.added(&path[..i], path[i]));
+    }
+    let mut best = None;
+    if board.is_complete() {
+        offer(
+            board,
+            &goal,
+            &path,
+            *scores.last().unwrap(),
+            &mut best,
+            &mut on_better,
+        );
+    }
+    // Lines still to draw, with and without a closing line.
+    let closing = board.report.wants(true) as usize;
+    let must_close = !board.report.wants(false) as usize;
+    loop {
+        match board.get_action() {
+            Mutation::Move => {
+                board.apply_best_move();
+                let score = scores.last().unwrap() + objective.added(&path, board.current);
+                path.push(board.current);
+                scores.push(score);
+                if board.is_complete() {
+                    offer(board, &goal, &path, score, &mut best, &mut on_better);
+                    continue;
+                }
+                let left = board.square_count() - path.len();
+                let hopeful = best.is_none_or(|best| {
+                    if goal.most {
+                        score + objective.range(board, &path, left + closing).1 > best
+                    } else {
+                        score + objective.range(board, &path, left + must_close).0 < best
+                    }
+                });
+                // Nothing below this square can beat the best tour.
+                if !hopeful {
+                    board.moves_to_make.last_mut().unwrap().clear();
+                }
+            }
+            Mutation::Rollback => {
+                board.progress.record(&board.moves_made);
+                board.progress.rollbacks += 1;
+                if board.moves_made.len() > board.floor {
+                    board.rollback();
+                    path.pop();
+                    scores.pop();
+                }
+                board.moves_to_make.pop();
+            }
+            Mutation::Stop => return Stopped::Exhausted,
+        }
+        if let Some(stopped) = board.progress.over(&board.budget) {
+            board.progress.record(&board.moves_made);
+            return stopped;
+        }
+    }
+}
+
+/// Sends the tour `board` has just completed on if it's of the kind
+/// reported and beats `best`, which it then becomes.
+fn offer<F: FnMut(&Board, f64)>(
+    board: &Board,
+    goal: &Goal,
+    path: &[Coord],
+    score: f64,
+    best: &mut Option<f64>,
+    on_better: &mut F,
+) {
+    let closed = board.is_closed_tour();
+    if !board.report.wants(closed) {
+        return;
+    }
+    let total = if closed {
+        score + goal.objective.added(path, path[0])
+    } else {
+        score
+    };
+    if goal.better(total, *best) {
+        *best = Some(total);
+        on_better(board, total);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::bitboard::BitOrder;
+use crate::coord::Coord;
+use crate::Board;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Decides which of the moves still to try from the current square is
+/// tried next. `candidates` is never empty.
+pub trait MoveOrder: Debug + Send + Sync {
+    /// Index into `candidates` of the move to make next.
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize;
+
+    /// How the bitboard engine can reproduce this order, if it can.
+    fn bit_order(&self) -> Option<BitOrder> {
+        None
+    }
+
+    /// True if the order draws on the board's random numbers, so a search
+    /// started again takes a different path.
+    fn is_random(&self) -> bool {
+        false
+    }
+}
+
+/// Indexes of the candidates leaving the fewest onward moves, in order.
+fn fewest_onward(board: &mut Board, candidates: &[Coord]) -> Vec<usize> {
+    let degrees: Vec<usize> = candidates.iter().map(|&m| board.degree_after(m)).collect();
+    let best = *degrees.iter().min().unwrap();
+    (0..candidates.len()).filter(|&i| degrees[i] == best).collect()
+}
+
+/// Picks the tied index scoring lowest, the first of those on a draw.
+fn lowest<K: Ord, F: FnMut(usize) -> K>(tied: &[usize], mut score: F) -> usize {
+    *tied.iter().min_by_key(|&&i| score(i)).unwrap()
+}
+
+/// Warnsdorff's rule: fewest onward moves, ties going to the first move in
+/// the piece's move order.
+#[derive(Debug)]
+pub struct Warnsdorff;
+
+impl MoveOrder for Warnsdorff {
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize {
+        fewest_onward(board, candidates)[0]
+    }
+
+    fn bit_order(&self) -> Option<BitOrder> {
+        Some(BitOrder::Warnsdorff)
+    }
+}
+
+/// Warnsdorff with Pohl's tie-break: of the tied moves, the one whose
+/// onward squares have the fewest onward moves between them.
+#[derive(Debug)]
+pub struct Pohl;
+
+impl MoveOrder for Pohl {
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize {
+        let tied = fewest_onward(board, candidates);
+        if tied.len() == 1 {
+            return tied[0];
+        }
+        lowest(&tied, |i| {
+            board.make_move(candidates[i]);
+            let sum: usize = board
+                .available_moves()
+                .into_iter()
+                .map(|m| board.degree_after(m))
+                .sum();
+            board.rollback();
+            sum
+        })
+    }
+}
+
+/// Warnsdorff with Arnd Roth's tie-break: of the tied moves, the one
+/// landing furthest from the centre of the board.
+#[derive(Debug)]
+pub struct Roth;
+
+impl MoveOrder for Roth {
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize {
+        let tied = fewest_onward(board, candidates);
+        let dims = board.shape.dims.clone();
+        let current = board.current;
+        lowest(&tied, |i| {
+            let to = board.step(current, candidates[i]).unwrap();
+            // Doubled so the centre of an even board stays on the grid.
+            let distance: i64 = dims
+                .iter()
+                .enumerate()
+                .map(|(axis, &d)| (2 * to[axis] as i64 - (d as i64 - 1)).pow(2))
+                .sum();
+            std::cmp::Reverse(distance)
+        })
+    }
+}
+
+/// Warnsdorff with ties going to the move that comes first in a ranking.
+/// Moves are numbered clockwise 1 to 8 starting from (1,-2), and a
+/// ranking lists them best first, e.g. 34261578. Moves outside the
+/// ranking come last. Later rankings can take over at set moves of the
+/// tour, the way Squirrel and Cull's algorithm switches sequences:
+/// 34261578/21578643@40 uses the second ranking from the 40th move on.
+#[derive(Debug)]
+pub struct RankedTies {
+    /// The move each ranking takes over at, the first at move 1.
+    stages: Vec<(usize, Vec<Coord>)>,
+}
+
+const CLOCKWISE: [(i16, i16); 8] = [
+    (1, -2),
+    (2, -1),
+    (2, 1),
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+];
+
+impl RankedTies {
+    pub fn new(rankings: &str) -> Result<RankedTies, String> {
+        let mut stages: Vec<(usize, Vec<Coord>)> = Vec::new();
+        for (i, stage) in rankings.split('/').enumerate() {
+            let (ranking, from) = match stage.split_once('@') {
+                Some((ranking, from)) if i > 0 => {
+                    let from = from
+                        .parse()
+                        .map_err(|_| format!("Bad move number '{}' in ranking", from))?;
+                    (ranking, from)
+                }
+                None if i == 0 => (stage, 1),
+                _ => {
+                    return Err(format!(
+                        "Bad ranking '{}', only the ones after the first need @MOVE",
+                        stage
+                    ))
+                }
+            };
+            if stages.last().is_some_and(|&(last, _)| from <= last) {
+                return Err("Each ranking has to take over later than the one before".to_string());
+            }
+            stages.push((from, parse_ranking(ranking)?));
+        }
+        Ok(RankedTies { stages })
+    }
+}
+
+/// The moves a ranking like 34261578 lists, best first.
+fn parse_ranking(ranking: &str) -> Result<Vec<Coord>, String> {
+    ranking
+        .chars()
+        .map(|ch| match ch.to_digit(10) {
+            Some(d @ 1..=8) => {
+                let (x, y) = CLOCKWISE[d as usize - 1];
+                Ok(Coord::xy(x, y))
+            }
+            _ => Err(format!("Bad move '{}' in ranking, expected 1 to 8", ch)),
+        })
+        .collect()
+}
+
+impl MoveOrder for RankedTies {
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize {
+        let tied = fewest_onward(board, candidates);
+        let number = board.moves_made.len() + 1;
+        let ranking = &self
+            .stages
+            .iter()
+            .rev()
+            .find(|&&(from, _)| from <= number)
+            .unwrap()
+            .1;
+        lowest(&tied, |i| {
+            ranking
+                .iter()
+                .position(|&m| m == candidates[i])
+                .unwrap_or(ranking.len())
+        })
+    }
+}
+
+/// No heuristic at all: moves are tried in the piece's move order.
+#[derive(Debug)]
+pub struct Lexicographic;
+
+impl MoveOrder for Lexicographic {
+    fn choose(&self, _board: &mut Board, _candidates: &[Coord]) -> usize {
+        0
+    }
+
+    fn bit_order(&self) -> Option<BitOrder> {
+        Some(BitOrder::Lexicographic)
+    }
+}
+
+/// Warnsdorff with ties broken at random from the board's seed.
+#[derive(Debug)]
+pub struct RandomTies;
+
+impl MoveOrder for RandomTies {
+    fn choose(&self, board: &mut Board, candidates: &[Coord]) -> usize {
+        let tied = fewest_onward(board, candidates);
+        tied[board.rng.below(tied.len())]
+    }
+
+    fn is_random(&self) -> bool {
+        true
+    }
+}
+
+pub fn by_name(name: &str) -> Result<Arc<dyn MoveOrder>, String> {
+    match name {
+        "warnsdorff" => Ok(Arc::new(Warnsdorff)),
+        "pohl" => Ok(Arc::new(Pohl)),
+        "roth" => Ok(Arc::new(Roth)),
+        "lexicographic" => Ok(Arc::new(Lexicographic)),
+        "random" => Ok(Arc::new(RandomTies)),
+        "ranked" => Ok(Arc::new(RankedTies::new("34261578")?)),
+        _ => match name.strip_prefix("ranked:") {
+            Some(ranking) => Ok(Arc::new(RankedTies::new(ranking)?)),
+            None => Err(format!(
+                "Unknown move order '{}', expected warnsdorff, pohl, roth, \
+                 ranked[:RANKING], lexicographic or random",
+                name
+            )),
+        },
+    }
+}
@@ -0,0 +1,80 @@
+use crate::coord::Coord;
+use crate::Board;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Runs `board`'s search on `board.threads` threads. The tree is cut
+/// `board.split_depth` moves below the current square and every subtree
+/// becomes a task; each thread starts with its own share of the tasks and
+/// steals from the back of the others' queues once it runs out.
+///
+/// Every thread explores its subtrees on its own `Board` and folds each
+/// complete tour into its own accumulator with `on_complete`. The
+/// accumulators are returned once all threads have finished.
+pub fn search<A, F>(board: &Board, on_complete: F) -> Vec<A>
+where
+    A: Default + Send,
+    F: Fn(&mut A, &Board) + Sync,
+{
+    let mut tasks = Vec::new();
+    split(&mut board.clone(), board.moves_made.len(), board.split_depth, &mut tasks);
+    let threads = board.threads.max(1);
+    let mut queues: Vec<VecDeque<Vec<Coord>>> = vec![VecDeque::new(); threads];
+    for (i, task) in tasks.into_iter().enumerate() {
+        queues[i % threads].push_back(task);
+    }
+    let queues: Vec<Mutex<VecDeque<Vec<Coord>>>> = queues.into_iter().map(Mutex::new).collect();
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|me| {
+                let (queues, on_complete) = (&queues, &on_complete);
+                scope.spawn(move || {
+                    let mut acc = A::default();
+                    while let Some(task) = next_task(queues, me) {
+                        let mut b = subtree(board, &task);
+                        b.search(|b| on_complete(&mut acc, b));
+                    }
+                    acc
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("Search thread panicked"))
+            .collect()
+    })
+}
+
+/// Own work first, oldest first; then the newest work of the others.
+fn next_task(queues: &[Mutex<VecDeque<Vec<Coord>>>], me: usize) -> Option<Vec<Coord>> {
+    if let Some(task) = queues[me].lock().unwrap().pop_front() {
+        return Some(task);
+    }
+    (1..queues.len())
+        .map(|i| (me + i) % queues.len())
+        .find_map(|victim| queues[victim].lock().unwrap().pop_back())
+}
+
+/// Collects the moves leading to every branch `depth` moves down, or to
+/// shallower tours that are already complete. Dead ends are dropped.
+fn split(board: &mut Board, base: usize, depth: usize, tasks: &mut Vec<Vec<Coord>>) {
+    if depth == 0 || board.is_complete() {
+        tasks.push(board.moves_made[base..].to_vec());
+        return;
+    }
+    for m in board.available_moves() {
+        board.make_move(m);
+        split(board, base, depth - 1, tasks);
+        board.rollback();
+    }
+}
+
+/// A single threaded board that only explores below `moves`.
+fn subtree(board: &Board, moves: &[Coord]) -> Board {
+    let mut ret = board.clone();
+    ret.threads = 1;
+    moves.iter().for_each(|&m| ret.make_move(m));
+    ret.pin_prefix();
+    ret
+}
@@ -0,0 +1,63 @@
+use crate::coord::{self, Coord};
+
+/// Named single leapers as (a,b) pairs.
+const LEAPERS: [(&str, i16, i16); 13] = [
+    ("wazir", 0, 1),
+    ("ferz", 1, 1),
+    ("dabbaba", 0, 2),
+    ("knight", 1, 2),
+    ("alfil", 2, 2),
+    ("threeleaper", 0, 3),
+    ("camel", 1, 3),
+    ("zebra", 2, 3),
+    ("tripper", 3, 3),
+    ("fourleaper", 0, 4),
+    ("giraffe", 1, 4),
+    ("stag", 2, 4),
+    ("antelope", 3, 4),
+];
+
+/// Named compound pieces, written the same way as on the command line.
+const COMPOUNDS: [(&str, &str); 3] = [
+    ("king", "wazir+ferz"),
+    ("centaur", "wazir+ferz+knight"),
+    ("squirrel", "dabbaba+knight+alfil"),
+];
+
+/// Moves of a piece built from `+` separated parts, each a name from
+/// `LEAPERS` or `COMPOUNDS` or an explicit `a,b` pair, e.g. `knight+camel`
+/// or `knight+1,4`. Moves shared by several parts are only listed once.
+pub fn moves_by_name(name: &str, dims: usize) -> Result<Vec<Coord>, String> {
+    let mut ret: Vec<Coord> = Vec::new();
+    for part in name.split('+').map(|p| p.trim()) {
+        let moves = if let Some(&(_, a, b)) = LEAPERS.iter().find(|l| l.0 == part) {
+            coord::leaper_moves(a, b, dims)?
+        } else if let Some(&(_, parts)) = COMPOUNDS.iter().find(|c| c.0 == part) {
+            moves_by_name(parts, dims)?
+        } else if let Some((a, b)) = part.split_once(',') {
+            let parse = |x: &str| {
+                x.trim()
+                    .parse::<i16>()
+                    .map_err(|_| format!("Bad leaper '{}' in piece '{}'", part, name))
+            };
+            coord::leaper_moves(parse(a)?, parse(b)?, dims)?
+        } else {
+            let known: Vec<&str> = LEAPERS
+                .iter()
+                .map(|l| l.0)
+                .chain(COMPOUNDS.iter().map(|c| c.0))
+                .collect();
+            return Err(format!(
+                "Unknown piece '{}', expected a,b or one of {}",
+                part,
+                known.join(", ")
+            ));
+        };
+        for m in moves {
+            if !ret.contains(&m) {
+                ret.push(m);
+            }
+        }
+    }
+    Ok(ret)
+}
@@ -0,0 +1,61 @@
+use crate::coord::Coord;
+use crate::tour::Tour;
+use crate::Board;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Most rotated paths tried before giving up on closing a tour.
+pub const MAX_ROTATIONS: usize = 10_000;
+
+/// Turns the complete open `tour` of `board` into a closed one by Pósa
+/// rotations, which Pólya and Schwenk used for tours too.
+///
+/// If the last square of the path a..v..w..z is a move away from some
+/// square v, swapping the edge v-w for v-z gives the path a..v z..w, which
+/// now ends on w. Rotations are tried breadth first, each new end square
+/// once, until the path ends a move away from its start. The first `keep`
+/// moves are never changed, so the start square and any prefix stay put.
+pub fn close(board: &Board, tour: &Tour, keep: usize) -> Result<Tour, String> {
+    let path = board.positions(tour);
+    if path.len() != board.square_count() {
+        return Err("Only complete tours can be closed".to_string());
+    }
+    let adjacent = |a: Coord, b: Coord| board.move_between(a, b).is_some();
+    let mut seen: HashSet<Coord> = HashSet::new();
+    seen.insert(*path.last().unwrap());
+    let mut queue = VecDeque::new();
+    queue.push_back(path);
+    while let Some(path) = queue.pop_front() {
+        let last = *path.last().unwrap();
+        if adjacent(last, path[0]) {
+            let moves = path
+                .iter()
+                .zip(path.iter().skip(1))
+                .map(|(&a, &b)| board.move_between(a, b).expect("Not a legal move"))
+                .collect();
+            return Ok(Tour {
+                start: path[0],
+                moves,
+                closed: true,
+                restarts: tour.restarts,
+            });
+        }
+        if seen.len() >= MAX_ROTATIONS {
+            continue;
+        }
+        let index: HashMap<Coord, usize> = path.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+        for pivot in board.moves.iter().filter_map(|&m| board.step(last, m)) {
+            let i = index[&pivot];
+            // The square before the last gives the same path back.
+            if i < keep || i + 2 >= path.len() || !seen.insert(path[i + 1]) {
+                continue;
+            }
+            let mut rotated = path[..=i].to_vec();
+            rotated.extend(path[i + 1..].iter().rev());
+            queue.push_back(rotated);
+        }
+    }
+    Err(format!(
+        "No rotation of the path closed it after trying {} end squares",
+        seen.len()
+    ))
+}
@@ -0,0 +1,418 @@
+use crate::coord::Coord;
+use crate::tour::Report;
+use crate::Board;
+use std::io::Write;
+
+/// A problem in conjunctive normal form: every clause needs at least one
+/// true literal. Variables are numbered from 1, and `-v` is the negation
+/// of `v`, as in DIMACS files.
+#[derive(Debug, Clone)]
+pub struct Cnf {
+    pub vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+/// Numbers the squares of `board` and the variables saying which square
+/// is visited at each step: variable `step * n + square + 1`, for `n`
+/// open squares.
+struct Encoding {
+    squares: Vec<Coord>,
+    /// Square number for each index of the shape, `None` for holes.
+    numbers: Vec<Option<usize>>,
+}
+
+impl Encoding {
+    fn new(board: &Board) -> Encoding {
+        let squares: Vec<Coord> = board.shape.squares().collect();
+        let mut numbers = vec![None; board.shape.len()];
+        for (i, &c) in squares.iter().enumerate() {
+            numbers[board.shape.index_of(c)] = Some(i);
+        }
+        Encoding { squares, numbers }
+    }
+
+    fn var(&self, square: usize, step: usize) -> i32 {
+        (step * self.squares.len() + square + 1) as i32
+    }
+
+    fn number(&self, board: &Board, c: Coord) -> usize {
+        self.numbers[board.shape.index_of(c)].expect("Not an open square")
+    }
+
+    /// Squares one move on from each square.
+    fn successors(&self, board: &Board) -> Vec<Vec<usize>> {
+        self.squares
+            .iter()
+            .map(|&a| {
+                let mut ret: Vec<usize> = Vec::new();
+                for b in board.moves.iter().filter_map(|&m| board.step(a, m)) {
+                    let b = self.number(board, b);
+                    if b != self.number(board, a) && !ret.contains(&b) {
+                        ret.push(b);
+                    }
+                }
+                ret
+            })
+            .collect()
+    }
+}
+
+/// Encodes the tours `board` would search for: one square per step, each
+/// square on one step, and a legal move between consecutive steps. Holes,
+/// wrapped edges and unusual pieces come from the board. The start square,
+/// end square, prefix and the kind of tour reported are constraints too.
+pub fn encode(board: &Board) -> Cnf {
+    let enc = Encoding::new(board);
+    let n = enc.squares.len();
+    let succ = enc.successors(board);
+    let mut pred = vec![Vec::new(); n];
+    for (a, bs) in succ.iter().enumerate() {
+        for &b in bs {
+            pred[b].push(a);
+        }
+    }
+    let mut clauses = Vec::new();
+    let exactly_one = |clauses: &mut Vec<Vec<i32>>, vars: Vec<i32>| {
+        for (i, &a) in vars.iter().enumerate() {
+            for &b in &vars[i + 1..] {
+                clauses.push(vec![-a, -b]);
+            }
+        }
+        clauses.push(vars);
+    };
+    for t in 0..n {
+        exactly_one(&mut clauses, (0..n).map(|s| enc.var(s, t)).collect());
+    }
+    for s in 0..n {
+        exactly_one(&mut clauses, (0..n).map(|t| enc.var(s, t)).collect());
+    }
+    // A square on one step means one of its successors on the next and
+    // one of its predecessors on the one before.
+    for t in 0..n.saturating_sub(1) {
+        for s in 0..n {
+            let mut next = vec![-enc.var(s, t)];
+            next.extend(succ[s].iter().map(|&b| enc.var(b, t + 1)));
+            clauses.push(next);
+            let mut prev = vec![-enc.var(s, t + 1)];
+            prev.extend(pred[s].iter().map(|&a| enc.var(a, t)));
+            clauses.push(prev);
+        }
+    }
+    let mut path = vec![board.start];
+    path.extend(&board.visited[..board.floor]);
+    for (t, &c) in path.iter().enumerate() {
+        clauses.push(vec![enc.var(enc.number(board, c), t)]);
+    }
+    if let Some(end) = board.end {
+        clauses.push(vec![enc.var(enc.number(board, end), n - 1)]);
+    }
+    let last = n - 1;
+    match board.report {
+        Report::Closed => {
+            for (s, bs) in succ.iter().enumerate() {
+                let mut back = vec![-enc.var(s, last)];
+                back.extend(bs.iter().map(|&b| enc.var(b, 0)));
+                clauses.push(back);
+            }
+        }
+        Report::Open => {
+            for (s, bs) in succ.iter().enumerate() {
+                clauses.extend(bs.iter().map(|&b| vec![-enc.var(s, last), -enc.var(b, 0)]));
+            }
+        }
+        Report::All => {}
+    }
+    Cnf {
+        vars: n * n,
+        clauses,
+    }
+}
+
+/// Writes `board`'s tour problem as a DIMACS CNF file at `path`.
+pub fn write_dimacs(board: &Board, path: &str) -> Result<(), String> {
+    let cnf = encode(board);
+    let enc = Encoding::new(board);
+    let mut text = format!(
+        "c Tours of {} squares: variable step * {} + square + 1 is true if\n\
+         c the square is visited on that step, counting both from 0.\n",
+        enc.squares.len(),
+        enc.squares.len()
+    );
+    for (i, &c) in enc.squares.iter().enumerate() {
+        text += &format!("c square {} is {}\n", i, board.show(c));
+    }
+    text += &format!("p cnf {} {}\n", cnf.vars, cnf.clauses.len());
+    for clause in &cnf.clauses {
+        for lit in clause {
+            text += &format!("{} ", lit);
+        }
+        text += "0\n";
+    }
+    std::fs::File::create(path)
+        .and_then(|mut f| f.write_all(text.as_bytes()))
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Reads the literals of a SAT solver's model, either in the competition
+/// format (`s SATISFIABLE` then `v` lines) or as bare numbers as MiniSat
+/// writes them after `SAT`.
+pub fn read_model(text: &str) -> Result<Vec<i32>, String> {
+    let mut ret = Vec::new();
+    for line in text.lines().map(|l| l.trim()) {
+        let first = line.split_whitespace().next().unwrap_or("");
+        match first {
+            "" | "c" | "SAT" => continue,
+            "UNSAT" => return Err("The solver found no tour".to_string()),
+            "s" => {
+                if line.contains("UNSATISFIABLE") {
+                    return Err("The solver found no tour".to_string());
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let numbers = line.strip_prefix('v').unwrap_or(line);
+        for x in numbers.split_whitespace() {
+            let lit: i32 = x
+                .parse()
+                .map_err(|_| format!("Bad literal '{}' in model", x))?;
+            if lit != 0 {
+                ret.push(lit);
+            }
+        }
+    }
+    if ret.is_empty() {
+        return Err("The model is empty".to_string());
+    }
+    Ok(ret)
+}
+
+/// The squares of the tour a model of `encode(board)` describes, in order.
+/// The tour is replayed on `board` to check it's one the board accepts.
+pub fn decode(board: &Board, model: &[i32]) -> Result<Vec<Coord>, String> {
+    let enc = Encoding::new(board);
+    let n = enc.squares.len();
+    let mut steps: Vec<Option<Coord>> = vec![None; n];
+    for &lit in model.iter().filter(|&&l| l > 0 && l as usize <= n * n) {
+        let (t, s) = ((lit as usize - 1) / n, (lit as usize - 1) % n);
+        if steps[t].is_some() {
+            return Err(format!("The model visits two squares on step {}", t));
+        }
+        steps[t] = Some(enc.squares[s]);
+    }
+    let path = steps
+        .iter()
+        .enumerate()
+        .map(|(t, c)| c.ok_or_else(|| format!("The model visits no square on step {}", t)))
+        .collect::<Result<Vec<Coord>, String>>()?;
+    let replay = board.clone().with_prefix(&path)?;
+    if !(replay.is_complete() && board.report.wants(replay.is_closed_tour())) {
+        return Err("The model isn't a tour of the kind asked for".to_string());
+    }
+    Ok(path)
+}
+
+/// Finds a model of `cnf` with the DPLL procedure: unit propagation over
+/// two watched literals per clause, branching on the lowest unassigned
+/// variable and backtracking chronologically. That's plenty for the tour
+/// encodings of small boards. Returns the model as one literal per
+/// variable, or `None` if there isn't one.
+pub fn solve(cnf: &Cnf) -> Option<Vec<i32>> {
+    let mut solver = Solver {
+        clauses: Vec::new(),
+        watches: vec![Vec::new(); 2 * cnf.vars + 2],
+        value: vec![0; cnf.vars + 1],
+        trail: Vec::new(),
+        head: 0,
+        decisions: Vec::new(),
+    };
+    for clause in &cnf.clauses {
+        let mut clause = clause.clone();
+        clause.sort_unstable();
+        clause.dedup();
+        if clause.iter().any(|&l| clause.contains(&-l)) {
+            continue;
+        }
+        match clause.len() {
+            0 => return None,
+            1 => match solver.value_of(clause[0]) {
+                -1 => return None,
+                0 => solver.assign(clause[0]),
+                _ => {}
+            },
+            _ => {
+                solver.watches[watch_index(clause[0])].push(solver.clauses.len());
+                solver.watches[watch_index(clause[1])].push(solver.clauses.len());
+                solver.clauses.push(clause);
+            }
+        }
+    }
+    solver.run()
+}
+
+fn watch_index(lit: i32) -> usize {
+    2 * lit.unsigned_abs() as usize + (lit < 0) as usize
+}
+
+struct Solver {
+    /// Clauses of two or more literals, the first two being watched.
+    clauses: Vec<Vec<i32>>,
+    /// Clauses watching each literal, by `watch_index`.
+    watches: Vec<Vec<usize>>,
+    /// 1 for true, -1 for false and 0 for unassigned, by variable.
+    value: Vec<i8>,
+    /// Literals made true, in order.
+    trail: Vec<i32>,
+    /// Literals before this point in `trail` have been propagated.
+    head: usize,
+    /// Trail length before each decision, the literal decided and
+    /// whether it's already the second try.
+    decisions: Vec<(usize, i32, bool)>,
+}
+
+impl Solver {
+    fn value_of(&self, lit: i32) -> i8 {
+        let v = self.value[lit.unsigned_abs() as usize];
+        if lit < 0 {
+            -v
+        } else {
+            v
+        }
+    }
+
+    fn assign(&mut self, lit: i32) {
+        self.value[lit.unsigned_abs() as usize] = if lit < 0 { -1 } else { 1 };
+        self.trail.push(lit);
+    }
+
+    /// Assigns every literal left alone in a clause, returning false on
+    /// a clause with no literal left.
+    fn propagate(&mut self) -> bool {
+        while self.head < self.trail.len() {
+            let false_lit = -self.trail[self.head];
+            self.head += 1;
+            let mut watching = std::mem::take(&mut self.watches[watch_index(false_lit)]);
+            let mut i = 0;
+            while i < watching.len() {
+                let ci = watching[i];
+                if self.clauses[ci][0] == false_lit {
+                    self.clauses[ci].swap(0, 1);
+                }
+                let other = self.clauses[ci][0];
+                if self.value_of(other) == 1 {
+                    i += 1;
+                    continue;
+                }
+                let replacement =
+                    (2..self.clauses[ci].len()).find(|&k| self.value_of(self.clauses[ci][k]) != -1);
+                if let Some(k) = replacement {
+                    self.clauses[ci].swap(1, k);
+                    self.watches[watch_index(self.clauses[ci][1])].push(ci);
+                    watching.swap_remove(i);
+                } else if self.value_of(other) == -1 {
+                    self.watches[watch_index(false_lit)] = watching;
+                    return false;
+                } else {
+                    self.assign(other);
+                    i += 1;
+                }
+            }
+            self.watches[watch_index(false_lit)] = watching;
+        }
+        true
+    }
+
+    /// Undoes the latest decision not yet tried both ways and tries the
+    /// other way, returning false once every decision has been.
+    fn backtrack(&mut self) -> bool {
+        while let Some((at, lit, second)) = self.decisions.pop() {
+            for undone in self.trail.drain(at..) {
+                self.value[undone.unsigned_abs() as usize] = 0;
+            }
+            self.head = at;
+            if !second {
+                self.decisions.push((at, -lit, true));
+                self.assign(-lit);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn run(&mut self) -> Option<Vec<i32>> {
+        loop {
+            if !self.propagate() {
+                if !self.backtrack() {
+                    return None;
+                }
+                continue;
+            }
+            match (1..self.value.len()).find(|&v| self.value[v] == 0) {
+                Some(v) => {
+                    self.decisions.push((self.trail.len(), v as i32, false));
+                    self.assign(v as i32);
+                }
+                None => {
+                    return Some(
+                        (1..self.value.len())
+                            .map(|v| {
+                                if self.value[v] > 0 {
+                                    v as i32
+                                } else {
+                                    -(v as i32)
+                                }
+                            })
+                            .collect(),
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads back the clauses of a DIMACS file, skipping its comments.
+    fn parse_dimacs(text: &str) -> Cnf {
+        let mut lines = text.lines().filter(|l| !l.starts_with('c'));
+        let header: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+        assert_eq!(header[..2], ["p", "cnf"]);
+        let clauses: Vec<Vec<i32>> = lines
+            .map(|l| {
+                let lits: Vec<i32> = l.split_whitespace().map(|x| x.parse().unwrap()).collect();
+                assert_eq!(lits.last(), Some(&0));
+                lits[..lits.len() - 1].to_vec()
+            })
+            .collect();
+        assert_eq!(clauses.len(), header[3].parse::<usize>().unwrap());
+        Cnf {
+            vars: header[2].parse().unwrap(),
+            clauses,
+        }
+    }
+
+    #[test]
+    fn dimacs_round_trip_gives_a_closed_tour() {
+        let board = Board::new(&[5, 6]);
+        let file = std::env::temp_dir().join(format!("knight_tour_{}.cnf", std::process::id()));
+        write_dimacs(&board, file.to_str().unwrap()).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        let model = solve(&parse_dimacs(&text)).expect("5x6 has closed tours");
+        let literals: Vec<String> = model.iter().map(|l| l.to_string()).collect();
+        let output = format!("s SATISFIABLE\nv {} 0\n", literals.join(" "));
+        let squares = decode(&board, &read_model(&output).unwrap()).unwrap();
+
+        assert_eq!(squares.len(), 30);
+        let tour = board.with_prefix(&squares).unwrap();
+        assert!(tour.is_complete() && tour.is_closed_tour());
+    }
+
+    #[test]
+    fn no_closed_tour_on_5x5() {
+        assert!(solve(&encode(&Board::new(&[5, 5]))).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::coord::{Coord, MAX_DIMS};
+
+/// Which squares of the bounding box a tour may visit.
+///
+/// Shapes can be read from a text grid where `.` is an available square
+/// and `#` is a hole, one row per line:
+///
+/// ```text
+/// #..#
+/// ....
+/// ....
+/// #..#
+/// ```
+#[derive(Debug, Clone)]
+pub struct Shape {
+    pub dims: Vec<i16>,
+    open: Vec<bool>,
+    open_count: usize,
+}
+
+impl Shape {
+    pub fn rect(width: i16, height: i16) -> Shape {
+        Shape::cuboid(&[width, height])
+    }
+
+    /// A box with one entry of `dims` per axis, e.g. `[4, 4, 4]`.
+    pub fn cuboid(dims: &[i16]) -> Shape {
+        assert!(
+            (2..=MAX_DIMS).contains(&dims.len()),
+            "Boards need 2 to {} dimensions",
+            MAX_DIMS
+        );
+        assert!(dims.iter().all(|&d| d > 0), "Board dimensions must be positive");
+        let len = dims.iter().map(|&d| d as usize).product();
+        Shape {
+            dims: dims.to_vec(),
+            open: vec![true; len],
+            open_count: len,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Shape, String> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(|l| l.trim_end())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        if width == 0 {
+            return Err("Shape is empty".to_string());
+        }
+        if width > i16::MAX as usize || rows.len() > i16::MAX as usize {
+            return Err(format!("Shape {}x{} is too large", width, rows.len()));
+        }
+        let mut ret = Shape::rect(width as i16, rows.len() as i16);
+        for (y, row) in rows.iter().enumerate() {
+            // Short rows are padded with holes.
+            for x in 0..width {
+                let open = match row.chars().nth(x) {
+                    Some('.') => true,
+                    Some('#') | None => false,
+                    Some(ch) => {
+                        return Err(format!(
+                            "Unexpected '{}' at row {} column {}, expected '.' or '#'",
+                            ch,
+                            y + 1,
+                            x + 1
+                        ))
+                    }
+                };
+                ret.set_open(Coord::xy(x as i16, y as i16), open);
+            }
+        }
+        if ret.open_count() == 0 {
+            return Err("Shape has no available squares".to_string());
+        }
+        Ok(ret)
+    }
+
+    pub fn load(path: &str) -> Result<Shape, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Shape::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn width(&self) -> i16 {
+        self.dims[0]
+    }
+
+    pub fn height(&self) -> i16 {
+        self.dims[1]
+    }
+
+    /// Number of squares in the bounding box, holes included.
+    pub fn len(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Position of `c` in a flat array with the last axis varying fastest.
+    pub fn index_of(&self, c: Coord) -> usize {
+        self.dims
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (axis, &d)| acc * d as usize + c[axis] as usize)
+    }
+
+    pub fn coord_of(&self, mut idx: usize) -> Coord {
+        let mut ret = Coord::default();
+        for (axis, &d) in self.dims.iter().enumerate().rev() {
+            ret[axis] = (idx % d as usize) as i16;
+            idx /= d as usize;
+        }
+        ret
+    }
+
+    /// True if `c` is inside the box, with nothing along the axes the
+    /// board doesn't have.
+    pub fn contains(&self, c: Coord) -> bool {
+        c.0[self.dims.len()..].iter().all(|&x| x == 0)
+            && self
+                .dims
+                .iter()
+                .enumerate()
+                .all(|(axis, &d)| c[axis] >= 0 && c[axis] < d)
+    }
+
+    /// True if `c` is inside the box and not a hole.
+    pub fn is_open(&self, c: Coord) -> bool {
+        self.contains(c) && self.open[self.index_of(c)]
+    }
+
+    pub fn set_open(&mut self, c: Coord, open: bool) {
+        let idx = self.index_of(c);
+        if self.open[idx] != open {
+            self.open[idx] = open;
+            self.open_count = if open { self.open_count + 1 } else { self.open_count - 1 };
+        }
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.open_count
+    }
+
+    /// Every available square in index order.
+    pub fn squares(&self) -> impl Iterator<Item = Coord> + '_ {
+        (0..self.len())
+            .filter(move |&i| self.open[i])
+            .map(move |i| self.coord_of(i))
+    }
+
+    /// The first available square in index order.
+    pub fn first_open(&self) -> Option<Coord> {
+        self.open.iter().position(|&o| o).map(|i| self.coord_of(i))
+    }
+}
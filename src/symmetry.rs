@@ -0,0 +1,165 @@
+use crate::coord::Coord;
+use crate::Board;
+
+/// A symmetry a closed tour can be made to have. The search only makes
+/// the first 1/`order` of the tour and each square it visits is taken
+/// along with its images, so the rest follows from the symmetry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Turning the board half way round gives the same tour.
+    Half,
+    /// So does turning it a quarter of the way, on square boards.
+    Quarter,
+    /// Flipping the board left to right gives the same tour run backwards.
+    Mirror,
+}
+
+impl Symmetry {
+    pub fn by_name(name: &str) -> Option<Symmetry> {
+        match name {
+            "180" => Some(Symmetry::Half),
+            "90" => Some(Symmetry::Quarter),
+            "mirror" => Some(Symmetry::Mirror),
+            _ => None,
+        }
+    }
+
+    /// How many copies of the first part of the tour make up the whole.
+    pub fn order(self) -> usize {
+        match self {
+            Symmetry::Half | Symmetry::Mirror => 2,
+            Symmetry::Quarter => 4,
+        }
+    }
+
+    /// Where the symmetry takes `c` on a board of size `dims`.
+    pub fn image(self, c: Coord, dims: &[i16]) -> Coord {
+        let (w, h) = (dims[0], dims[1]);
+        match self {
+            Symmetry::Half => Coord::xy(w - 1 - c.x(), h - 1 - c.y()),
+            Symmetry::Quarter => Coord::xy(w - 1 - c.y(), c.x()),
+            Symmetry::Mirror => Coord::xy(w - 1 - c.x(), c.y()),
+        }
+    }
+
+    /// The squares `c` is taken to by the symmetry applied once, twice
+    /// and so on, with the number each gets if `c` is visited `value`th
+    /// in a first part of `len` squares.
+    pub fn images(self, c: Coord, value: usize, len: usize, dims: &[i16]) -> Vec<(Coord, usize)> {
+        match self {
+            // The mirror half runs backwards.
+            Symmetry::Mirror => vec![(self.image(c, dims), 2 * len + 1 - value)],
+            _ => {
+                let mut image = c;
+                (1..self.order())
+                    .map(|j| {
+                        image = self.image(image, dims);
+                        (image, value + j * len)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Fails unless `board` looks the same after the symmetry, moves and
+    /// all, no square is its own image, some square can start a tour and
+    /// the parts can join up on the right colours.
+    pub fn check(self, board: &Board) -> Result<(), String> {
+        let dims = &board.shape.dims;
+        if dims.len() != 2 {
+            return Err("Symmetric tours need a 2D board".to_string());
+        }
+        if self == Symmetry::Quarter && dims[0] != dims[1] {
+            return Err("Quarter turn symmetry needs a square board".to_string());
+        }
+        for a in board.shape.squares() {
+            let b = self.image(a, dims);
+            if b == a || self.image(b, dims) == a && self == Symmetry::Quarter {
+                return Err(format!(
+                    "Square {} is its own image, so no tour can have that symmetry",
+                    board.show(a)
+                ));
+            }
+            if !board.is_on_board(b) {
+                return Err("The shape doesn't have that symmetry".to_string());
+            }
+            let moves_match = board
+                .moves
+                .iter()
+                .filter_map(|&m| board.step(a, m))
+                .all(|to| board.move_between(b, self.image(to, dims)).is_some());
+            if !moves_match {
+                return Err("The piece or topology doesn't have that symmetry".to_string());
+            }
+        }
+        if !board.shape.squares().any(|c| self.can_start(board, c)) {
+            return Err("No square has a move to its mirror image, so no tour can have that symmetry".to_string());
+        }
+        // A piece that changes colour every move puts square len + 1, the
+        // start's image, on the start's colour iff len is even.
+        let len = board.square_count() / self.order();
+        let start = board.start;
+        let flips = colour(start) != colour(self.image(start, dims));
+        if self != Symmetry::Mirror && switches_colour(board) && flips != (len % 2 == 1) {
+            return Err(format!(
+                "The piece changes colour every move, so with {} squares in each part \
+                 the tour can't close up with that symmetry",
+                len
+            ));
+        }
+        Ok(())
+    }
+
+    /// True if a first part of the tour from `start` to `last` closes up
+    /// into a whole tour with its images. A mirrored part also needs
+    /// `can_start`, which the search checks before its first move.
+    pub fn closes(self, board: &Board, start: Coord, last: Coord) -> bool {
+        let dims = &board.shape.dims;
+        match self {
+            Symmetry::Mirror => board.move_between(last, self.image(last, dims)).is_some(),
+            _ => board.move_between(last, self.image(start, dims)).is_some(),
+        }
+    }
+
+    /// False if no tour from `start` can have the symmetry at all. The
+    /// mirror image of a tour runs backwards, so a mirrored tour has to
+    /// cross between the halves with one move at each end of its first
+    /// part, and that needs a move from `start` to its image.
+    pub fn can_start(self, board: &Board, start: Coord) -> bool {
+        self != Symmetry::Mirror
+            || board
+                .move_between(start, self.image(start, &board.shape.dims))
+                .is_some()
+    }
+
+    /// The whole tour made from its first part `path`.
+    pub fn expand(self, path: &[Coord], dims: &[i16]) -> Vec<Coord> {
+        let mut ret = path.to_vec();
+        match self {
+            Symmetry::Mirror => ret.extend(path.iter().rev().map(|&c| self.image(c, dims))),
+            _ => {
+                let mut part = path.to_vec();
+                for _ in 1..self.order() {
+                    part = part.iter().map(|&c| self.image(c, dims)).collect();
+                    ret.extend(&part);
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn colour(c: Coord) -> bool {
+    (c.x() + c.y()) % 2 != 0
+}
+
+/// True if every move on `board` goes to a square of the other colour.
+fn switches_colour(board: &Board) -> bool {
+    board.shape.squares().all(|a| {
+        board
+            .moves
+            .iter()
+            .filter_map(|&m| board.step(a, m))
+            .all(|b| colour(a) != colour(b))
+    })
+}
@@ -0,0 +1,107 @@
+use crate::coord::Coord;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// How the edges of the board are glued together.
+///
+/// A move is first added to the current square without any checks and the
+/// result handed to `wrap`, which either maps it back into the box given by
+/// `dims` or rejects it by returning `None`. The twisted topologies glue
+/// the first axis with a flip of the second; any further axes never wrap.
+pub trait Topology: Debug + Send + Sync {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord>;
+
+    /// True if nothing is glued, so every move is a straight line on the
+    /// board as drawn.
+    fn is_plane(&self) -> bool {
+        false
+    }
+}
+
+/// Checks every axis from `first` onwards is inside the board.
+fn in_range(c: Coord, dims: &[i16], first: usize) -> Option<Coord> {
+    if (first..dims.len()).all(|axis| c[axis] >= 0 && c[axis] < dims[axis]) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+fn wrapped(mut c: Coord, dims: &[i16], axis: usize) -> Coord {
+    c[axis] = c[axis].rem_euclid(dims[axis]);
+    c
+}
+
+/// The ordinary flat board, nothing wraps.
+#[derive(Debug)]
+pub struct Plane;
+
+impl Topology for Plane {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord> {
+        in_range(c, dims, 0)
+    }
+
+    fn is_plane(&self) -> bool {
+        true
+    }
+}
+
+/// The two ends of the first axis are glued, e.g. left and right edges.
+#[derive(Debug)]
+pub struct Cylinder;
+
+impl Topology for Cylinder {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord> {
+        in_range(wrapped(c, dims, 0), dims, 1)
+    }
+}
+
+/// Every pair of opposite faces is glued.
+#[derive(Debug)]
+pub struct Torus;
+
+impl Topology for Torus {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord> {
+        Some((0..dims.len()).fold(c, |c, axis| wrapped(c, dims, axis)))
+    }
+}
+
+/// Left and right edges are glued with a half twist, so crossing them
+/// flips the board upside down.
+#[derive(Debug)]
+pub struct Mobius;
+
+impl Topology for Mobius {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord> {
+        in_range(twisted(in_range(c, dims, 1)?, dims), dims, 0)
+    }
+}
+
+/// A Möbius strip whose top and bottom edges are also glued.
+#[derive(Debug)]
+pub struct Klein;
+
+impl Topology for Klein {
+    fn wrap(&self, c: Coord, dims: &[i16]) -> Option<Coord> {
+        in_range(twisted(wrapped(c, dims, 1), dims), dims, 0)
+    }
+}
+
+/// Wraps the first axis around, flipping the second once for every pass
+/// over the twisted edge.
+fn twisted(mut c: Coord, dims: &[i16]) -> Coord {
+    if c[0].div_euclid(dims[0]) % 2 != 0 {
+        c[1] = dims[1] - 1 - c[1];
+    }
+    wrapped(c, dims, 0)
+}
+pub fn by_name(name: &str) -> Option<Arc<dyn Topology>> {
+    match name {
+        "plane" => Some(Arc::new(Plane)),
+        "cylinder" => Some(Arc::new(Cylinder)),
+        "torus" => Some(Arc::new(Torus)),
+        "mobius" => Some(Arc::new(Mobius)),
+        "klein" => Some(Arc::new(Klein)),
+        _ => None,
+    }
+}
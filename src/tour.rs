@@ -0,0 +1,40 @@
+use crate::coord::Coord;
+
+/// A tour as reported by the search: the square it starts on and the moves
+/// made from there.
+#[derive(Debug, Clone)]
+pub struct Tour {
+    pub start: Coord,
+    pub moves: Vec<Coord>,
+    /// The last square is a move away from the first.
+    pub closed: bool,
+    /// Times the search started again since the tour before this one.
+    pub restarts: usize,
+}
+
+/// Which complete tours the search sends on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Report {
+    Closed,
+    Open,
+    All,
+}
+
+impl Report {
+    pub fn wants(self, closed: bool) -> bool {
+        match self {
+            Report::Closed => closed,
+            Report::Open => !closed,
+            Report::All => true,
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Report> {
+        match name {
+            "closed" => Some(Report::Closed),
+            "open" => Some(Report::Open),
+            "all" => Some(Report::All),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::budget::Stopped;
+use crate::coord::Coord;
+use crate::geometry;
+use crate::tour::Tour;
+use crate::Board;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Fails unless the path already played on `board`, its prefix, never
+/// crosses itself.
+pub fn check(board: &Board) -> Result<(), String> {
+    let path = board.positions(&board.tour());
+    for i in 2..path.len() {
+        if geometry::crosses_path(&path[..i], path[i - 1], path[i]) {
+            return Err(format!(
+                "The prefix crosses itself going from {} to {}",
+                board.show(path[i - 1]),
+                board.show(path[i])
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The path played so far, drawn open whether or not its ends are a move
+/// apart, since the closing line could cross the others.
+pub fn path(board: &Board) -> Tour {
+    Tour {
+        closed: false,
+        ..board.tour()
+    }
+}
+
+/// Looks for the longest path whose lines, drawn from centre to centre,
+/// never meet except where one ends and the next begins: Yarbrough's
+/// uncrossed knight's path. Searches from every square in turn, or only
+/// on from the prefix if `board` has one, calling `on_longer` with each
+/// path longer than every one before it. Once every branch has been walked
+/// the last path sent is the longest there is; the budget can stop the
+/// search before that. Either way `board` is left on the start of the
+/// longest path, which is `board.progress.best`.
+pub fn search<F: FnMut(&Board)>(board: &mut Board, mut on_longer: F) -> Stopped {
+    board.progress.started.get_or_insert_with(Instant::now);
+    let starts: Vec<Coord> = if board.floor > 0 {
+        vec![board.start]
+    } else {
+        board.shape.squares().collect()
+    };
+    let mut best_start = board.start;
+    let mut stopped = None;
+    for s in starts {
+        if board.floor == 0 {
+            move_start(board, s);
+        }
+        let mut path = board.positions(&board.tour());
+        stopped = extend(board, &mut path, &mut best_start, &mut on_longer);
+        if stopped.is_some() {
+            break;
+        }
+    }
+    if board.floor == 0 {
+        move_start(board, best_start);
+    }
+    stopped.unwrap_or(Stopped::Exhausted)
+}
+
+/// Starts `board` afresh from `start`, keeping its progress.
+fn move_start(board: &mut Board, start: Coord) {
+    let progress = std::mem::take(&mut board.progress);
+    board.start = start;
+    board.reset();
+    board.progress = progress;
+}
+
+/// Tries every uncrossed way on from the end of `path`, depth first.
+fn extend<F: FnMut(&Board)>(
+    board: &mut Board,
+    path: &mut Vec<Coord>,
+    best_start: &mut Coord,
+    on_longer: &mut F,
+) -> Option<Stopped> {
+    // However the path goes on, it can't visit more squares than it can reach.
+    if board.moves_made.len() + reachable(board) <= board.progress.best.len() {
+        return None;
+    }
+    let current = board.current;
+    for m in board.moves.clone() {
+        match board.step(current, m) {
+            Some(to) if board.can_move(to) && !geometry::crosses_path(path, current, to) => {
+                board.make_move(m);
+                path.push(to);
+                board.progress.nodes += 1;
+                if board.moves_made.len() > board.progress.best.len() {
+                    board.progress.record(&board.moves_made);
+                    *best_start = board.start;
+                    on_longer(board);
+                }
+                let stopped = board
+                    .progress
+                    .over(&board.budget)
+                    .or_else(|| extend(board, path, best_start, on_longer));
+                board.rollback();
+                path.pop();
+                if stopped.is_some() {
+                    return stopped;
+                }
+            }
+            _ => {}
+        }
+    }
+    board.progress.rollbacks += 1;
+    None
+}
+
+/// Unvisited squares the path could get to from where it is, ignoring
+/// crossings.
+fn reachable(board: &Board) -> usize {
+    let mut seen = vec![false; board.shape.len()];
+    seen[board.shape.index_of(board.current)] = true;
+    let mut queue = VecDeque::from(vec![board.current]);
+    let mut ret = 0;
+    while let Some(c) = queue.pop_front() {
+        for to in board.moves.iter().filter_map(|&m| board.step(c, m)) {
+            let i = board.shape.index_of(to);
+            if !seen[i] && board.can_move(to) {
+                seen[i] = true;
+                ret += 1;
+                queue.push_back(to);
+            }
+        }
+    }
+    ret
+}